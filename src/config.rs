@@ -0,0 +1,333 @@
+use std::{fs, path::{Path, PathBuf}};
+
+use crate::{errors::ConfigError, groupthem, wordguess};
+
+/// Prefix of the environment variables, ex. `WORD_GAMES_PORT`
+static ENV_PREFIX: &str = "WORD_GAMES_";
+/// The config file read when none is given
+static DEFAULT_CONFIG_FILE: &str = "word_games.toml";
+
+/// Every setting with a short description, used for `--help`
+static SETTINGS: [(&str, &str); 11] = [
+    ("bind_address", "The address to listen on (127.0.0.1)"),
+    ("port", "The port to listen on (8080)"),
+    ("database_path", "The SQLite database file (database.sqlite)"),
+    ("database_pool_size", "The maximum number of open database connections (8)"),
+    ("json_limit", "The maximum size of a JSON request body in bytes (200)"),
+    ("maximum_guesses", "The default number of guesses in a WordGuess game (6)"),
+    ("maximum_bad_guesses", "The number of bad guesses allowed in a GroupThem game (4)"),
+    ("word_list", "A custom list of accepted 5 letter WordGuess guesses, one word per line"),
+    ("answer_list", "The possible WordGuess answers. Defaults to every word in `word_list`"),
+    ("puzzle_source", "Where GroupThem puzzles come from: `scraper`, `directory` or `cache` (scraper)"),
+    ("puzzle_directory", "The directory read by the `directory` puzzle source"),
+];
+
+/// The server settings
+///
+/// Settings are read in order, later sources overriding earlier ones:
+/// 1. The defaults
+/// 2. The config file: `--config`, `WORD_GAMES_CONFIG` or `word_games.toml` when it exists
+/// 3. Environment variables, ex. `WORD_GAMES_PORT=80`
+/// 4. Command line flags, ex. `--port 80` or `--port=80`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Config {
+    pub bind_address: String,
+    pub port: u16,
+    pub database_path: String,
+    pub database_pool_size: u32,
+    pub json_limit: usize,
+    pub maximum_guesses: usize,
+    pub maximum_bad_guesses: u8,
+    pub word_list: Option<PathBuf>,
+    pub answer_list: Option<PathBuf>,
+    pub puzzle_source: String,
+    pub puzzle_directory: Option<PathBuf>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            bind_address: "127.0.0.1".to_string(),
+            port: 8080,
+            database_path: "database.sqlite".to_string(),
+            database_pool_size: 8,
+            json_limit: 200,
+            maximum_guesses: wordguess::MAXIMUM_GUESSES,
+            maximum_bad_guesses: groupthem::MAXIMUM_BAD_GUESSES,
+            word_list: None,
+            answer_list: None,
+            puzzle_source: "scraper".to_string(),
+            puzzle_directory: None,
+        }
+    }
+}
+
+impl Config {
+    /// Load the settings from the config file, the environment and the command line
+    /// ### Arguments
+    /// * `args` - The command line arguments, without the program name
+    /// * `env` - The environment variables
+    pub fn load(
+        args: impl IntoIterator<Item = String>,
+        env: impl IntoIterator<Item = (String, String)>,
+    ) -> Result<Self, ConfigError> {
+        let flags = parse_flags(args)?;
+        let env: Vec<(String, String)> = env
+            .into_iter()
+            .filter_map(|(key, value)| {
+                key.strip_prefix(ENV_PREFIX)
+                    .map(|key| (key.to_lowercase(), value))
+            })
+            .collect();
+
+        let mut config = Config::default();
+
+        // An explicit config file has to exist, the default one is optional
+        let explicit_file = flags
+            .iter()
+            .chain(env.iter())
+            .rev()
+            .find(|(key, _)| key == "config")
+            .map(|(_, path)| PathBuf::from(path));
+        match explicit_file {
+            Some(path) => config.apply_file(&path)?,
+            None if Path::new(DEFAULT_CONFIG_FILE).exists() => {
+                config.apply_file(Path::new(DEFAULT_CONFIG_FILE))?
+            }
+            None => {}
+        }
+
+        for (key, value) in env.iter().chain(flags.iter()) {
+            if key != "config" {
+                config.set(key, value)?;
+            }
+        }
+
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Check the settings that parse but can't be served
+    /// ### Errors
+    /// Returns a `ConfigError::InvalidValue` for the first setting out of its range
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        fn invalid(key: &str, value: impl ToString, reason: String) -> Result<(), ConfigError> {
+            Err(ConfigError::InvalidValue {
+                key: key.to_string(),
+                value: value.to_string(),
+                reason,
+            })
+        }
+
+        if self.database_pool_size == 0 {
+            return invalid("database_pool_size", self.database_pool_size, "the pool needs at least one connection".to_string());
+        }
+        if self.json_limit == 0 {
+            return invalid("json_limit", self.json_limit, "every request body would be rejected".to_string());
+        }
+        if !wordguess::GUESS_LIMITS.contains(&self.maximum_guesses) {
+            return invalid(
+                "maximum_guesses",
+                self.maximum_guesses,
+                format!(
+                    "a game has {} to {} guesses",
+                    wordguess::GUESS_LIMITS.start(),
+                    wordguess::GUESS_LIMITS.end()
+                ),
+            );
+        }
+        if self.maximum_bad_guesses == 0 {
+            return invalid("maximum_bad_guesses", self.maximum_bad_guesses, "a game allows at least one bad guess".to_string());
+        }
+        Ok(())
+    }
+
+    /// Read a TOML config file, ex.
+    /// ```toml
+    /// bind_address = "0.0.0.0"
+    /// port = 8080
+    /// ```
+    fn apply_file(&mut self, path: &Path) -> Result<(), ConfigError> {
+        let contents = fs::read_to_string(path)
+            .map_err(|e| ConfigError::FileError(format!("{}: {}", path.display(), e)))?;
+        let table: toml::Table = toml::from_str(&contents)
+            .map_err(|e| ConfigError::FileError(format!("{}: {}", path.display(), e)))?;
+
+        for (key, value) in table {
+            let value = match value {
+                toml::Value::String(value) => value,
+                value => value.to_string(),
+            };
+            self.set(&key, &value)?;
+        }
+        Ok(())
+    }
+
+    /// Set a single setting from its text value
+    /// ### Errors
+    /// Returns a `ConfigError` if the setting doesn't exist or the value can't be parsed
+    pub fn set(&mut self, key: &str, value: &str) -> Result<(), ConfigError> {
+        fn parse<T: std::str::FromStr>(key: &str, value: &str) -> Result<T, ConfigError>
+        where
+            T::Err: std::fmt::Display,
+        {
+            value.trim().parse().map_err(|e: T::Err| ConfigError::InvalidValue {
+                key: key.to_string(),
+                value: value.to_string(),
+                reason: e.to_string(),
+            })
+        }
+
+        match key.replace('-', "_").as_str() {
+            "bind_address" => self.bind_address = value.to_string(),
+            "port" => self.port = parse(key, value)?,
+            "database_path" => self.database_path = value.to_string(),
+            "database_pool_size" => self.database_pool_size = parse(key, value)?,
+            "json_limit" => self.json_limit = parse(key, value)?,
+            "maximum_guesses" => self.maximum_guesses = parse(key, value)?,
+            "maximum_bad_guesses" => self.maximum_bad_guesses = parse(key, value)?,
+            "word_list" => self.word_list = Some(PathBuf::from(value)),
+            "answer_list" => self.answer_list = Some(PathBuf::from(value)),
+            "puzzle_source" => self.puzzle_source = value.to_string(),
+            "puzzle_directory" => self.puzzle_directory = Some(PathBuf::from(value)),
+            _ => return Err(ConfigError::UnknownSetting(key.to_string())),
+        }
+        Ok(())
+    }
+}
+
+/// The usage shown by `--help`
+pub fn usage() -> String {
+    let mut usage = "Usage: word_games [--config <file>] [--<setting> <value>]...\n\nSettings:\n".to_string();
+    for (name, description) in SETTINGS {
+        usage.push_str(&format!(
+            "  --{:<22} {}{}\n    {}\n",
+            name.replace('_', "-"),
+            ENV_PREFIX,
+            name.to_uppercase(),
+            description
+        ));
+    }
+    usage
+}
+
+/// Parse `--key value` and `--key=value` flags
+fn parse_flags(args: impl IntoIterator<Item = String>) -> Result<Vec<(String, String)>, ConfigError> {
+    let mut flags = Vec::new();
+    let mut args = args.into_iter();
+
+    while let Some(arg) = args.next() {
+        let flag = arg
+            .strip_prefix("--")
+            .ok_or(ConfigError::UnknownSetting(arg.clone()))?;
+
+        let (key, value) = match flag.split_once('=') {
+            Some((key, value)) => (key.to_string(), value.to_string()),
+            None => {
+                let value = args
+                    .next()
+                    .ok_or(ConfigError::MissingValue(flag.to_string()))?;
+                (flag.to_string(), value)
+            }
+        };
+        flags.push((key.replace('-', "_"), value));
+    }
+
+    Ok(flags)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    fn env(vars: &[(&str, &str)]) -> Vec<(String, String)> {
+        vars.iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_defaults() {
+        // An explicit empty file, so a `word_games.toml` in the working directory isn't read
+        let path = std::env::temp_dir().join(format!("word_games_{}.toml", crate::token::create_token()));
+        fs::write(&path, "").unwrap();
+
+        let config = Config::load(args(&["--config", path.to_str().unwrap()]), env(&[("PATH", "/usr/bin")]));
+        fs::remove_file(path).unwrap();
+        assert_eq!(config.unwrap(), Config::default());
+    }
+
+    #[test]
+    fn test_precedence() {
+        let path = std::env::temp_dir().join(format!("word_games_{}.toml", crate::token::create_token()));
+        fs::write(&path, "port = 9000\nbind_address = \"0.0.0.0\"\nmaximum_guesses = 8\n").unwrap();
+
+        let config = Config::load(
+            args(&["--config", path.to_str().unwrap(), "--maximum-guesses=10"]),
+            env(&[("WORD_GAMES_PORT", "9001"), ("WORD_GAMES_MAXIMUM_GUESSES", "9")]),
+        )
+        .unwrap();
+        fs::remove_file(path).unwrap();
+
+        // The file is overridden by the environment, which is overridden by flags
+        assert_eq!(config.bind_address, "0.0.0.0");
+        assert_eq!(config.port, 9001);
+        assert_eq!(config.maximum_guesses, 10);
+    }
+
+    #[test]
+    fn test_invalid_settings() {
+        assert!(matches!(
+            Config::load(args(&["--port", "eighty"]), env(&[])),
+            Err(ConfigError::InvalidValue { .. })
+        ));
+        assert!(matches!(
+            Config::load(args(&["--colour", "blue"]), env(&[])),
+            Err(ConfigError::UnknownSetting(_))
+        ));
+        assert!(matches!(
+            Config::load(args(&["--port"]), env(&[])),
+            Err(ConfigError::MissingValue(_))
+        ));
+        assert!(matches!(
+            Config::load(args(&["--config", "/does/not/exist.toml"]), env(&[])),
+            Err(ConfigError::FileError(_))
+        ));
+    }
+
+    #[test]
+    fn test_validate() {
+        assert!(Config::default().validate().is_ok());
+
+        for (key, value) in [
+            ("database_pool_size", "0"),
+            ("json_limit", "0"),
+            ("maximum_guesses", "0"),
+            ("maximum_guesses", "21"),
+            ("maximum_bad_guesses", "0"),
+        ] {
+            let mut config = Config::default();
+            config.set(key, value).unwrap();
+            assert!(
+                matches!(config.validate(), Err(ConfigError::InvalidValue { key: ref invalid, .. }) if invalid == key),
+                "{} = {}",
+                key,
+                value
+            );
+        }
+    }
+
+    #[test]
+    fn test_usage_lists_every_setting() {
+        let usage = usage();
+        let mut config = Config::default();
+        for (name, _) in SETTINGS {
+            assert!(usage.contains(&format!("--{}", name.replace('_', "-"))));
+            assert!(config.set(name, "1").is_ok());
+        }
+    }
+}
@@ -1,42 +1,66 @@
-use std::collections::HashMap;
-
-use chrono::{Duration, Local};
-use rusqlite::{params, Connection};
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use r2d2_sqlite::SqliteConnectionManager;
+use rusqlite::{params, types::Type, Connection, OptionalExtension, Transaction, TransactionBehavior};
 use serde::de::DeserializeOwned;
-use crate::models::{GameStatus, User, UserRequest};
-use crate::errors::DatabaseError;
-use crate::token::create_token;
+use crate::models::{GuessRecord, StoredState, User, UserRequest, GAME_STATUS_VERSION};
+use crate::errors::{DatabaseError, SessionError};
+use crate::migrations;
+use crate::registry::GameMetadata;
+use crate::token::{create_token, fingerprint};
+
+/// A pool of connections to the database, shared by every worker
+pub type Pool = r2d2::Pool<SqliteConnectionManager>;
 
-/// Initializes a connection to the database
+/// Creates the pool of database connections and migrates the database to the latest schema
 /// ### Arguments
-/// * `in_memory` - A boolean indicating whether to use an in-memory database.\
+/// * `path` - The database file, or `None` to use an in-memory database.\
+/// * `size` - The maximum number of open connections
 /// 
-/// In-memory databases are useful for testing and development as they are destroyed when the program exits.
-pub fn initialize_connection(in_memory: bool) -> rusqlite::Connection {
-    let users_table_creation = "CREATE TABLE IF NOT EXISTS users (
-                stale_token TEXT PRIMARY KEY,
-                fresh_token TEXT NOT NULL,
-                game_status TEXT
-            )";
-
-    let game_cache_table_creation = "CREATE TABLE IF NOT EXISTS game_cache (
-                day_id TEXT PRIMARY KEY,
-                game_cache TEXT NOT NULL
-            )";
-
-    let conn = if in_memory {
-        rusqlite::Connection::open_in_memory().expect("Failed to open in-memory database")
-    } else {
-        rusqlite::Connection::open("database.sqlite").expect("Failed to open database")
+/// In-memory databases are useful for testing and development as they are destroyed when the program exits.\
+/// Every in-memory connection is a separate database, so an in-memory pool only holds one connection.
+/// ### Errors
+/// Returns a `DatabaseError` if the database can't be opened or migrated
+pub fn initialize_pool(path: Option<&str>, size: u32) -> Result<Pool, DatabaseError> {
+    let (manager, size) = match path {
+        // WAL lets readers work while a guess is written, and writers wait for the lock instead of failing
+        Some(path) => (
+            SqliteConnectionManager::file(path).with_init(|conn| {
+                conn.execute_batch(
+                    "PRAGMA journal_mode = WAL;
+                     PRAGMA synchronous = NORMAL;
+                     PRAGMA busy_timeout = 5000;",
+                )
+            }),
+            size,
+        ),
+        None => (SqliteConnectionManager::memory(), 1),
     };
 
-    // Execute table creation statements
-    conn.execute(users_table_creation, []).expect("Failed to create users table");
-    conn.execute(game_cache_table_creation, []).expect("Failed to create game_cache table");
+    let pool = r2d2::Pool::builder().max_size(size).build(manager)?;
+    migrations::migrate(&*pool.get()?)?;
 
-    conn
+    Ok(pool)
+}
+
+/// Runs database work on a pooled connection, on the blocking thread pool.\
+/// SQLite calls block, so they must never run on the async workers
+/// ### Arguments
+/// * `pool` - The connection pool
+/// * `work` - The queries to run
+pub async fn run<F, R>(pool: &Pool, work: F) -> Result<R, DatabaseError>
+where
+    F: FnOnce(&Connection) -> Result<R, DatabaseError> + Send + 'static,
+    R: Send + 'static,
+{
+    let pool = pool.clone();
+    actix_web::web::block(move || work(&*pool.get()?))
+        .await
+        .map_err(|e| DatabaseError::PoolError(e.to_string()))?
 }
 
+/// How long the previous fresh token of a user is still accepted after it was replaced.\
+/// Covers two requests sent with the same token, ex. two tabs or parallel bots
+pub static FRESH_TOKEN_GRACE_SECONDS: i64 = 30;
 
 /// Retrieves a user from the database or creates a new user (IN-MEMORY) if it does not exist.
 /// ### Arguments
@@ -45,38 +69,68 @@ pub fn initialize_connection(in_memory: bool) -> rusqlite::Connection {
 /// ### Returns
 /// A `User` struct
 /// ### Errors
-/// Returns a `DatabaseError` if the user does not exist or if there is an issue with the database.
+/// Returns a `DatabaseError::SessionError` if the tokens don't match a user:
+/// * `UnknownSession` - The stale token was never handed out
+/// * `TokenReplayed` - The previous fresh token was used after its grace period, the session may have been stolen.
+///   The session is revoked, no fresh token is accepted anymore
+/// * `InvalidToken` - Any other fresh token
 /// ### IMPORTANT
 /// This **does not** insert a new user to the database. It only retrieves or creates a user in-memory.
-/// A user must be updated with `update_user_game_status` to be inserted into the database.
+/// A user must be updated with `record_guess` to be inserted into the database.
 pub fn get_user(conn: &Connection, request: UserRequest) -> Result<User, DatabaseError> {
     match request {
         UserRequest::Tokens {
             stale_token,
             fresh_token,
         } => {
-            let mut stmt = conn.prepare(
-                "SELECT stale_token, fresh_token, game_status FROM users WHERE stale_token = ? AND fresh_token = ?",
-            ).map_err(DatabaseError::FromSQLError)?;
-
-            let mut rows = stmt.query(params![stale_token, fresh_token])
-                .map_err(DatabaseError::FromSQLError)?;
-
-            if let Some(row) = rows.next().map_err(DatabaseError::FromSQLError)? {
-                let game_status_json: String = row.get(2).map_err(DatabaseError::FromSQLError)?;
-                let game_status_map: HashMap<String, GameStatus> =
-                    serde_json::from_str(&game_status_json)
-                        .map_err(|e| DatabaseError::GameStatusParseError(e.to_string()))?;
-
-                Ok(User {
-                    stale_token: row.get(0).map_err(DatabaseError::FromSQLError)?,
-                    fresh_token: Some(row.get(1).map_err(DatabaseError::FromSQLError)?),
-                    game_status: game_status_map,
-                })
-            } else {
-                Err(DatabaseError::FromSQLError(
-                    rusqlite::Error::QueryReturnedNoRows,
-                ))
+            let session: Option<(String, Option<String>, Option<String>)> = conn
+                .query_row(
+                    "SELECT fresh_token, previous_fresh_token, rotated_at FROM users WHERE stale_token = ?1",
+                    [&stale_token],
+                    |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+                )
+                .optional()?;
+
+            let Some((current, previous, rotated_at)) = session else {
+                return Err(SessionError::UnknownSession.into());
+            };
+
+            // A revoked session has no fresh token
+            if current.is_empty() {
+                return Err(SessionError::InvalidToken.into());
+            }
+
+            if fresh_token == current {
+                return Ok(User {
+                    stale_token,
+                    fresh_token: Some(fresh_token),
+                });
+            }
+
+            if previous.as_ref() != Some(&fresh_token) {
+                return Err(SessionError::InvalidToken.into());
+            }
+
+            // A request that raced the one that rotated the token
+            let rotated_at = rotated_at
+                .and_then(|rotated_at| DateTime::parse_from_rfc3339(&rotated_at).ok())
+                .map(|rotated_at| rotated_at.with_timezone(&Utc));
+            match rotated_at {
+                Some(rotated_at) if Utc::now() - rotated_at <= Duration::seconds(FRESH_TOKEN_GRACE_SECONDS) => {
+                    Ok(User {
+                        stale_token,
+                        fresh_token: Some(fresh_token),
+                    })
+                }
+                _ => {
+                    // Whoever holds the current token can't be told apart from a thief, so neither keeps the session
+                    conn.execute(
+                        "UPDATE users SET fresh_token = '', previous_fresh_token = NULL, rotated_at = NULL WHERE stale_token = ?1",
+                        [&stale_token],
+                    )?;
+                    eprintln!("Replayed fresh token, revoked session {}", fingerprint(&stale_token));
+                    Err(SessionError::TokenReplayed.into())
+                }
             }
         }
         UserRequest::NewUser => {
@@ -85,68 +139,263 @@ pub fn get_user(conn: &Connection, request: UserRequest) -> Result<User, Databas
             Ok(User {
                 stale_token: new_stale_token,
                 fresh_token: None,
-                game_status: HashMap::new(),
             })
         }
     }
 }
 
-pub fn update_user_game_status(
+/// Inserts a new user without any game.\
+/// Used to hand out session tokens to programmatic players before their first guess
+pub fn create_user(conn: &Connection) -> Result<User, DatabaseError> {
+    let stale_token = create_token();
+    let fresh_token = create_token();
+
+    conn.execute(
+        "INSERT INTO users (stale_token, fresh_token) VALUES (?1, ?2)",
+        params![stale_token, fresh_token],
+    )?;
+
+    Ok(User {
+        stale_token,
+        fresh_token: Some(fresh_token),
+    })
+}
+
+/// The `puzzle_date` column of a puzzle day
+/// Ex. `2025-01-31`
+fn puzzle_date(day: NaiveDate) -> String {
+    day.format("%Y-%m-%d").to_string()
+}
+
+/// Rebuilds a user's game from its guesses and the feedback they got
+/// ### Arguments
+/// * `conn` - A reference to the database connection
+/// * `stale_token` - The user
+/// * `day` - The day of the puzzle
+/// ### Returns
+/// The game and the number of guesses it was rebuilt from, or `None` if the user hasn't made a guess in it
+/// ### Errors
+/// Returns a `DatabaseError::GameStatusParseError` if the game can't be rebuilt from its guesses.\
+/// The stored feedback is used as is, so changes to the word lists or the scoring never break a stored game
+pub fn get_game<G: GameMetadata>(
     conn: &Connection,
     stale_token: &str,
-    new_game_status: &GameStatus,
-) -> Result<String, DatabaseError> {
-    let new_fresh_token = create_token();
+    day: NaiveDate,
+) -> Result<Option<(G, usize)>, DatabaseError> {
+    let game = G::KEY;
+    let initial_state: Option<String> = conn
+        .query_row(
+            "SELECT initial_state FROM games WHERE stale_token = ?1 AND game = ?2 AND puzzle_date = ?3",
+            params![stale_token, game, puzzle_date(day)],
+            |row| row.get(0),
+        )
+        .optional()?;
 
-    let key = new_game_status.to_string();
+    let Some(initial_state) = initial_state else {
+        return Ok(None);
+    };
+    let initial_state: StoredState<G> = serde_json::from_str(&initial_state)
+        .map_err(|e| DatabaseError::GameStatusParseError(e.to_string()))?;
+    if initial_state.version != GAME_STATUS_VERSION || initial_state.game != game {
+        return Err(DatabaseError::GameStatusParseError(format!(
+            "Expected a version {} {} state, got a version {} {} state",
+            GAME_STATUS_VERSION, game, initial_state.version, initial_state.game
+        )));
+    }
 
-    // Retrieve the existing game status from the database
-    let mut stmt = conn.prepare("SELECT game_status FROM users WHERE stale_token = ?1")
-        .map_err(DatabaseError::FromSQLError)?;
+    let mut stmt = conn.prepare(
+        "SELECT guess, result FROM guesses WHERE stale_token = ?1 AND game = ?2 AND puzzle_date = ?3 ORDER BY id",
+    )?;
+    let guesses = stmt
+        .query_map(params![stale_token, game, puzzle_date(day)], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+        })?
+        .map(|row| {
+            let (guess, result) = row?;
+            let json = |text: &str| -> Result<serde_json::Value, DatabaseError> {
+                serde_json::from_str(text).map_err(|e| DatabaseError::GameStatusParseError(e.to_string()))
+            };
+            Ok((json(&guess)?, json(&result)?))
+        })
+        .collect::<Result<Vec<(serde_json::Value, serde_json::Value)>, DatabaseError>>()?;
+
+    let saved_guesses = guesses.len();
+    initial_state
+        .state
+        .replay(guesses)
+        .map(|game| Some((game, saved_guesses)))
+        .map_err(|e| DatabaseError::GameStatusParseError(format!("Unable to restore a guess: {}", e)))
+}
 
-    let mut rows = stmt.query(params![stale_token])
-        .map_err(DatabaseError::FromSQLError)?;
+/// Replaces the fresh token a user presented, inside the transaction that stores their guess
+/// ### Arguments
+/// * `conn` - The transaction of the guess
+/// * `stale_token` - The user
+/// * `presented` - The fresh token sent with the request
+/// * `new_fresh_token` - The token that replaces it
+/// ### Returns
+/// The fresh token the user has to send next.\
+/// The previous token, within its grace period, gets the current token instead of replacing it again, so
+/// two requests sent with the same token end up with the same one
+/// ### Errors
+/// The errors of `get_user`, as the session may have been rotated or revoked since the request was checked
+fn rotate_fresh_token(
+    conn: &Connection,
+    stale_token: &str,
+    presented: &str,
+    new_fresh_token: &str,
+) -> Result<String, DatabaseError> {
+    get_user(
+        conn,
+        UserRequest::Tokens {
+            stale_token: stale_token.to_string(),
+            fresh_token: presented.to_string(),
+        },
+    )?;
 
-    let mut game_status_map: HashMap<String, GameStatus> = if let Some(row) = rows.next().map_err(DatabaseError::FromSQLError)? {
-        let game_status_json: String = row.get(0).map_err(DatabaseError::FromSQLError)?;
-        serde_json::from_str(&game_status_json).map_err(|e| DatabaseError::GameStatusParseError(e.to_string()))?
-    } else {
-        // If no existing entry, create a new HashMap
-        HashMap::new()
+    // The replaced fresh token is kept for the grace period
+    let rotated = conn.execute(
+        "UPDATE users SET previous_fresh_token = fresh_token, fresh_token = ?3, rotated_at = ?4
+             WHERE stale_token = ?1 AND fresh_token = ?2",
+        params![stale_token, presented, new_fresh_token, Utc::now().to_rfc3339()],
+    )?;
+    if rotated == 1 {
+        return Ok(new_fresh_token.to_string());
+    }
+
+    conn.query_row(
+        "SELECT fresh_token FROM users WHERE stale_token = ?1 AND previous_fresh_token = ?2",
+        params![stale_token, presented],
+        |row| row.get(0),
+    )
+    .optional()?
+    .ok_or(SessionError::InvalidToken.into())
+}
+
+/// Stores a guess and rotates the user's fresh token.\
+/// New users are inserted into the database
+/// ### Arguments
+/// * `conn` - A reference to the database connection
+/// * `user` - The user, as returned by `get_user`
+/// * `before` - The game before the guess
+/// * `saved_guesses` - The number of stored guesses `before` was rebuilt from, 0 for a new game
+/// * `guess` - The guess as sent by the player
+/// * `after` - The game after the guess
+/// ### Returns
+/// The new fresh token of the user
+/// ### Errors
+/// * `DatabaseError::GuessConflict` - Another guess of the game was saved since `before` was read
+/// * `DatabaseError::SessionError` - The fresh token of `user` was replaced or revoked since it was checked
+pub fn record_guess<G: GameMetadata>(
+    conn: &Connection,
+    user: &User,
+    before: &G,
+    saved_guesses: usize,
+    guess: &G::Guess,
+    after: &G,
+) -> Result<String, DatabaseError> {
+    let stale_token = user.stale_token.as_str();
+    let game = G::KEY;
+    let day = puzzle_date(after.day());
+
+    // Take the write lock up front, so concurrent guesses wait for each other instead of
+    // failing to upgrade a read
+    let transaction = Transaction::new_unchecked(conn, TransactionBehavior::Immediate)?;
+
+    let fresh_token = match &user.fresh_token {
+        Some(presented) => match rotate_fresh_token(&transaction, stale_token, presented, &create_token()) {
+            // Keep the revocation of a replayed token
+            Err(e @ DatabaseError::SessionError(SessionError::TokenReplayed)) => {
+                transaction.commit()?;
+                return Err(e);
+            }
+            fresh_token => fresh_token?,
+        },
+        None => {
+            let fresh_token = create_token();
+            transaction.execute(
+                "INSERT INTO users (stale_token, fresh_token) VALUES (?1, ?2)",
+                params![stale_token, fresh_token],
+            )?;
+            fresh_token
+        }
     };
 
-    // Update the HashMap with the new game status
-    game_status_map.insert(key.to_string(), new_game_status.clone());
+    // A guess saved since `before` was read would be replayed before this one
+    let stored_guesses: usize = transaction.query_row(
+        "SELECT COUNT(*) FROM guesses WHERE stale_token = ?1 AND game = ?2 AND puzzle_date = ?3",
+        params![stale_token, game, day],
+        |row| row.get(0),
+    )?;
+    if stored_guesses != saved_guesses {
+        return Err(DatabaseError::GuessConflict);
+    }
 
-    // Serialize the updated HashMap back to JSON
-    let new_game_status_json = serde_json::to_string(&game_status_map)
+    // The first guess of a game also stores the state it started from
+    if transaction
+        .query_row(
+            "SELECT 1 FROM games WHERE stale_token = ?1 AND game = ?2 AND puzzle_date = ?3",
+            params![stale_token, game, day],
+            |_| Ok(()),
+        )
+        .optional()?
+        .is_none()
+    {
+        let initial_state = serde_json::to_string(&StoredState {
+            version: GAME_STATUS_VERSION,
+            game: game.to_string(),
+            state: before,
+        })
         .map_err(|e| DatabaseError::GameStatusParseError(e.to_string()))?;
+        transaction.execute(
+            "INSERT INTO games (stale_token, game, puzzle_date, initial_state) VALUES (?1, ?2, ?3, ?4)",
+            params![stale_token, game, day, initial_state],
+        )?;
+    }
 
-    // Insert or update the user record in the database
-    conn.execute(
-        "INSERT INTO users (stale_token, fresh_token, game_status) VALUES (?1, ?2, ?3)
-             ON CONFLICT(stale_token) DO UPDATE SET
-             fresh_token = excluded.fresh_token,
-             game_status = excluded.game_status",
-        params![stale_token, new_fresh_token, new_game_status_json],
-    )
-    .map_err(DatabaseError::FromSQLError)?;
+    transaction.execute(
+        "INSERT INTO guesses (stale_token, game, puzzle_date, guess, result) VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![stale_token, game, day, serde_json::json!(guess).to_string(), serde_json::json!(after.last_result()).to_string()],
+    )?;
+    transaction.commit()?;
 
-    Ok(new_fresh_token)
+    Ok(fresh_token)
+}
+
+/// Every guess of a user, oldest first
+#[allow(dead_code)]
+pub fn get_guess_history(conn: &Connection, stale_token: &str) -> Result<Vec<GuessRecord>, DatabaseError> {
+    let mut stmt = conn.prepare(
+        "SELECT id, game, puzzle_date, guess, result, created_at FROM guesses WHERE stale_token = ?1 ORDER BY id",
+    )?;
+    let records = stmt.query_map([stale_token], |row| {
+        let json = |i: usize| -> rusqlite::Result<serde_json::Value> {
+            serde_json::from_str(&row.get::<_, String>(i)?)
+                .map_err(|e| rusqlite::Error::FromSqlConversionFailure(i, Type::Text, Box::new(e)))
+        };
+        let puzzle_date: String = row.get(2)?;
+
+        Ok(GuessRecord {
+            id: row.get(0)?,
+            game: row.get(1)?,
+            puzzle_date: NaiveDate::parse_from_str(&puzzle_date, "%Y-%m-%d")
+                .map_err(|e| rusqlite::Error::FromSqlConversionFailure(2, Type::Text, Box::new(e)))?,
+            guess: json(3)?,
+            result: json(4)?,
+            created_at: row.get(5)?,
+        })
+    })?;
+
+    Ok(records.collect::<Result<Vec<GuessRecord>, rusqlite::Error>>()?)
 }
 
 #[allow(dead_code)]
 pub fn get_all_users(conn: &Connection) -> Result<Vec<User>, DatabaseError> {
-    let mut stmt = conn.prepare("SELECT stale_token, fresh_token, game_status FROM users")?;
+    let mut stmt = conn.prepare("SELECT stale_token, fresh_token FROM users")?;
     let user_iter = stmt.query_map([], |row| {
-        let game_status: String = row.get(2)?;
-        let game_status: HashMap<String, GameStatus> = serde_json::from_str(&game_status)
-            .map_err(|_| rusqlite::Error::InvalidQuery)?;
-
         Ok(User {
             stale_token: row.get(0)?,
             fresh_token: Some(row.get(1)?),
-            game_status,
         })
     })?;
 
@@ -158,68 +407,57 @@ pub fn get_all_users(conn: &Connection) -> Result<Vec<User>, DatabaseError> {
     Ok(users)
 }
 
+/// Removes every player. The schema and the cached puzzles are kept
 #[allow(dead_code)]
 pub fn reset_database(conn: &Connection) -> Result<(), DatabaseError> {
-    conn.execute("DROP TABLE IF EXISTS users", [])?;
-    conn.execute(
-        "CREATE TABLE users (
-            stale_token TEXT PRIMARY KEY,
-            fresh_token TEXT NOT NULL,
-            game_status TEXT
-        )",
-        [],
+    conn.execute_batch(
+        "DELETE FROM guesses;
+         DELETE FROM games;
+         DELETE FROM users;",
     )?;
     Ok(())
 }
 
-pub fn store_game_cache<T>(conn: &Connection, game: &T, day_offset: usize) -> Result<(), DatabaseError> 
+/// The `game_cache` key of a puzzle day
+/// Ex. `january012025`
+fn game_cache_day_id(day: NaiveDate) -> String {
+    day.format("%B-%d-%Y")
+        .to_string()
+        .to_lowercase()
+        .chars()
+        .filter(|c| c.is_alphanumeric())
+        .collect::<String>()
+}
+
+pub fn store_game_cache<T>(conn: &Connection, game: &T, day: NaiveDate) -> Result<(), DatabaseError> 
 where T: serde::Serialize
 {
     let game_cache_json = serde_json::to_string(game)
         .map_err(|e| DatabaseError::GameStatusParseError(e.to_string()))?;
 
-    // Use the day_offset to generate a unique formatted string
-    let yesterday = Local::now() - Duration::days(day_offset as i64);
-    let yesterday_formatted = yesterday.format("%B-%d-%Y")
-        .to_string()
-        .to_lowercase()
-        .chars()
-        .filter(|c| c.is_alphanumeric())
-        .collect::<String>();
-    
-    // Use `yesterday_formatted` as the primary key
+    // Use the puzzle day as the primary key
     conn.execute(
         "INSERT INTO game_cache (day_id, game_cache) VALUES (?1, ?2)
              ON CONFLICT(day_id) DO UPDATE SET
              game_cache = excluded.game_cache",
-        params![yesterday_formatted, game_cache_json],
+        params![game_cache_day_id(day), game_cache_json],
     )
     .map_err(DatabaseError::FromSQLError)?;
 
     Ok(())
 }
 
-pub fn get_game_cache<T>(conn: &Connection, day_offset: usize) -> Result<T, DatabaseError>
+pub fn get_game_cache<T>(conn: &Connection, day: NaiveDate) -> Result<T, DatabaseError>
 where
     T: DeserializeOwned,
 {
-    // Calculate the unique ID using the day_offset
-    let yesterday = Local::now() - Duration::days(day_offset as i64);
-    let yesterday_formatted = yesterday
-        .format("%B-%d-%Y")
-        .to_string()
-        .to_lowercase()
-        .chars()
-        .filter(|c| c.is_alphanumeric())
-        .collect::<String>();
-
     // SQL query to get the game cache entry
     let mut stmt = conn.prepare("SELECT game_cache FROM game_cache WHERE day_id = ?1")
         .map_err(DatabaseError::FromSQLError)?;
 
     // Execute the query and fetch the result
     let game_cache_json: String = stmt
-        .query_row([&yesterday_formatted], |row| row.get(0))
+        .query_row([&game_cache_day_id(day)], |row| row.get(0))
         .map_err(DatabaseError::FromSQLError)?;
 
     // Deserialize the JSON string into the expected type T
@@ -232,21 +470,34 @@ where
 #[cfg(test)]
 mod tests {
     use super::*;
-    use rusqlite::Connection;
-    use crate::wordguess::WordGuess;
+    use chrono::{Duration, Local};
+    use r2d2::PooledConnection;
+    use serde_json::json;
+    use crate::game::Game;
+    use crate::wordguess::{score, WordGuess, MAXIMUM_GUESSES};
+
+    fn setup_test_db() -> PooledConnection<SqliteConnectionManager> {
+        initialize_pool(None, 1).unwrap().get().unwrap()
+    }
 
-    fn setup_test_db() -> Connection {
-        let conn = initialize_connection(true);
-        conn.execute(
-            "CREATE TABLE users (
-                stale_token TEXT PRIMARY KEY,
-                fresh_token TEXT NOT NULL,
-                game_status TEXT
-            )",
-            [],
+    /// Make a WordGuess guess for a user and store it, then hand the user their new fresh token
+    fn guess(conn: &Connection, user: &mut User, day: NaiveDate, word: &str) -> String {
+        let (before, saved_guesses) = get_game::<WordGuess>(conn, &user.stale_token, day)
+            .unwrap()
+            .unwrap_or_else(|| (WordGuess::new(day, MAXIMUM_GUESSES), 0));
+        let after = before.guess(word).unwrap();
+
+        let fresh_token = record_guess(
+            conn,
+            user,
+            &before,
+            saved_guesses,
+            &word.to_string(),
+            &after,
         )
         .unwrap();
-        conn
+        user.fresh_token = Some(fresh_token.clone());
+        fresh_token
     }
 
     #[test]
@@ -262,7 +513,7 @@ mod tests {
 
         let user = get_user(&conn, user_request).unwrap();
 
-        assert!(user.stale_token.len() > 0);
+        assert!(!user.stale_token.is_empty());
         assert!(user.fresh_token.is_none());
 
     }
@@ -272,11 +523,9 @@ mod tests {
         let conn = setup_test_db();
         let user_request = UserRequest::NewUser;
         // Create new user locally
-        let user = get_user(&conn, user_request).unwrap();
-
-        let new_game_status = GameStatus::WordGuess(WordGuess::new());
+        let mut user = get_user(&conn, user_request).unwrap();
 
-        let new_token = update_user_game_status(&conn, &user.stale_token, &new_game_status).unwrap();
+        let new_token = guess(&conn, &mut user, Local::now().date_naive(), "crane");
 
         // Update the user with the new game status
         let updated_user = get_user(
@@ -294,6 +543,28 @@ mod tests {
         assert!(updated_user.fresh_token.is_some());
     }
 
+    #[test]
+    fn test_create_user() {
+        let conn = setup_test_db();
+        let mut user = create_user(&conn).unwrap();
+
+        // The user can be found before making a guess
+        let found = get_user(
+            &conn,
+            UserRequest::Tokens {
+                stale_token: user.stale_token.clone(),
+                fresh_token: user.fresh_token.clone().unwrap(),
+            },
+        )
+        .unwrap();
+        assert_eq!(found.stale_token, user.stale_token);
+
+        // Their first guess rotates the fresh token
+        let first_token = user.fresh_token.clone();
+        let fresh_token = guess(&conn, &mut user, Local::now().date_naive(), "crane");
+        assert_ne!(Some(fresh_token), first_token);
+    }
+
     #[test]
     fn test_invalid_stale_token() {
         let conn = setup_test_db();
@@ -307,6 +578,7 @@ mod tests {
         );
 
         assert!(result.is_err());
+        assert!(matches!(result, Err(DatabaseError::SessionError(SessionError::UnknownSession))));
     }
 
     #[test]
@@ -326,6 +598,235 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_previous_fresh_token() {
+        let conn = setup_test_db();
+        let mut user = create_user(&conn).unwrap();
+        let first_token = user.fresh_token.clone().unwrap();
+        let stale_token = user.stale_token.clone();
+        let tokens = |fresh_token: &str| UserRequest::Tokens {
+            stale_token: stale_token.clone(),
+            fresh_token: fresh_token.to_string(),
+        };
+
+        let second_token = guess(&conn, &mut user, Local::now().date_naive(), "crane");
+
+        // A request racing the rotation still gets through
+        assert!(get_user(&conn, tokens(&first_token)).is_ok());
+        assert!(get_user(&conn, tokens(&second_token)).is_ok());
+
+        // Only the latest replaced token is remembered
+        let third_token = guess(&conn, &mut user, Local::now().date_naive(), "slate");
+        assert!(matches!(
+            get_user(&conn, tokens(&first_token)),
+            Err(DatabaseError::SessionError(SessionError::InvalidToken))
+        ));
+
+        // After the grace period the previous token looks stolen
+        conn.execute(
+            "UPDATE users SET rotated_at = ?1 WHERE stale_token = ?2",
+            params![
+                (Utc::now() - Duration::seconds(FRESH_TOKEN_GRACE_SECONDS + 1)).to_rfc3339(),
+                stale_token
+            ],
+        )
+        .unwrap();
+        assert!(matches!(
+            get_user(&conn, tokens(&second_token)),
+            Err(DatabaseError::SessionError(SessionError::TokenReplayed))
+        ));
+
+        // The session is revoked, even for the current token
+        for token in [&second_token, &third_token, ""] {
+            assert!(matches!(
+                get_user(&conn, tokens(token)),
+                Err(DatabaseError::SessionError(SessionError::InvalidToken))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_revoked_before_guess_is_stored() {
+        let conn = setup_test_db();
+        let mut user = create_user(&conn).unwrap();
+        let first_token = user.fresh_token.clone().unwrap();
+        let day = Local::now().date_naive();
+        guess(&conn, &mut user, day, "crane");
+
+        // The guess is checked, then the replaced token is replayed before it is stored
+        let checked = get_user(
+            &conn,
+            UserRequest::Tokens {
+                stale_token: user.stale_token.clone(),
+                fresh_token: user.fresh_token.clone().unwrap(),
+            },
+        )
+        .unwrap();
+        conn.execute(
+            "UPDATE users SET rotated_at = ?1 WHERE stale_token = ?2",
+            params![
+                (Utc::now() - Duration::seconds(FRESH_TOKEN_GRACE_SECONDS + 1)).to_rfc3339(),
+                user.stale_token
+            ],
+        )
+        .unwrap();
+        let replayed = UserRequest::Tokens {
+            stale_token: user.stale_token.clone(),
+            fresh_token: first_token,
+        };
+        assert!(matches!(
+            get_user(&conn, replayed),
+            Err(DatabaseError::SessionError(SessionError::TokenReplayed))
+        ));
+
+        let (before, saved_guesses) = get_game::<WordGuess>(&conn, &user.stale_token, day).unwrap().unwrap();
+        let after = before.clone().guess("slate").unwrap();
+        assert!(matches!(
+            record_guess(&conn, &checked, &before, saved_guesses, &"slate".to_string(), &after),
+            Err(DatabaseError::SessionError(SessionError::InvalidToken))
+        ));
+
+        // The session stays revoked and the guess isn't stored
+        let fresh_token: String = conn
+            .query_row("SELECT fresh_token FROM users WHERE stale_token = ?1", [&user.stale_token], |row| row.get(0))
+            .unwrap();
+        assert_eq!(fresh_token, "");
+        assert_eq!(get_guess_history(&conn, &user.stale_token).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_racing_guesses_share_fresh_token() {
+        let conn = setup_test_db();
+        let user = create_user(&conn).unwrap();
+        let today = Local::now().date_naive();
+        let yesterday = today - Duration::days(1);
+
+        // Two guesses sent with the same token, on different games
+        let mut first = user.clone();
+        let mut second = user.clone();
+        let first_token = guess(&conn, &mut first, today, "crane");
+        let second_token = guess(&conn, &mut second, yesterday, "slate");
+        assert_eq!(first_token, second_token);
+        assert_ne!(Some(first_token.clone()), user.fresh_token);
+
+        // Neither player is left with a token that looks replayed later
+        conn.execute(
+            "UPDATE users SET rotated_at = ?1 WHERE stale_token = ?2",
+            params![
+                (Utc::now() - Duration::seconds(FRESH_TOKEN_GRACE_SECONDS + 1)).to_rfc3339(),
+                user.stale_token
+            ],
+        )
+        .unwrap();
+        let tokens = UserRequest::Tokens {
+            stale_token: user.stale_token.clone(),
+            fresh_token: first_token,
+        };
+        assert!(get_user(&conn, tokens).is_ok());
+    }
+
+    #[test]
+    fn test_guess_conflict() {
+        let conn = setup_test_db();
+        let mut user = get_user(&conn, UserRequest::NewUser).unwrap();
+        let day = Local::now().date_naive();
+        let before = WordGuess::new(day, MAXIMUM_GUESSES);
+
+        // Two requests read the same game, the second one to save loses
+        guess(&conn, &mut user, day, "crane");
+        let result = record_guess(&conn, &user, &before, 0, &"slate".to_string(), &before);
+        assert!(matches!(result, Err(DatabaseError::GuessConflict)));
+
+        match get_game::<WordGuess>(&conn, &user.stale_token, day).unwrap() {
+            Some((word_guess, saved_guesses)) => {
+                assert_eq!(word_guess.to_vec(), ["crane"]);
+                assert_eq!(saved_guesses, 1);
+            }
+            other => panic!("Expected a WordGuess, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_game_status_kept_per_day() {
+        let conn = setup_test_db();
+        let mut user = get_user(&conn, UserRequest::NewUser).unwrap();
+
+        let yesterday = Local::now().date_naive() - Duration::days(1);
+        let today = Local::now().date_naive();
+        guess(&conn, &mut user, yesterday, "crane");
+        guess(&conn, &mut user, today, "slate");
+
+        // Yesterday's game is kept as history next to today's
+        for (day, word) in [(yesterday, "crane"), (today, "slate")] {
+            match get_game::<WordGuess>(&conn, &user.stale_token, day).unwrap() {
+                Some((word_guess, _)) => {
+                    assert_eq!(word_guess.day, day);
+                    assert_eq!(word_guess.to_vec(), [word]);
+                }
+                other => panic!("Expected a WordGuess for {}, got {:?}", day, other),
+            }
+        }
+
+        let tomorrow = today + Duration::days(1);
+        assert!(get_game::<WordGuess>(&conn, &user.stale_token, tomorrow).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_replay_guess_history() {
+        let conn = setup_test_db();
+        let mut user = get_user(&conn, UserRequest::NewUser).unwrap();
+        let day = NaiveDate::from_ymd_opt(2025, 1, 1).unwrap();
+
+        let mut state = WordGuess::new(day, MAXIMUM_GUESSES);
+        for word in ["crane", "slate", "pious"] {
+            guess(&conn, &mut user, day, word);
+            state = state.guess(word).unwrap();
+        }
+
+        // The game is rebuilt from its guesses
+        match get_game::<WordGuess>(&conn, &user.stale_token, day).unwrap() {
+            Some((word_guess, _)) => {
+                assert_eq!(word_guess.to_vec(), state.to_vec());
+                assert_eq!(word_guess.answer, state.answer);
+                assert_eq!(json!(word_guess), json!(state));
+            }
+            other => panic!("Expected a WordGuess, got {:?}", other),
+        }
+
+        // Every guess is a row with its feedback
+        let history = get_guess_history(&conn, &user.stale_token).unwrap();
+        assert_eq!(history.len(), 3);
+        for (record, result) in history.iter().zip(&state.guesses) {
+            assert_eq!(record.game, "word_guess");
+            assert_eq!(record.puzzle_date, day);
+            assert_eq!(record.guess, json!(result.to_string()));
+            assert_eq!(record.result, json!(result));
+        }
+    }
+
+    #[test]
+    fn test_restore_stored_feedback() {
+        let conn = setup_test_db();
+        let user = get_user(&conn, UserRequest::NewUser).unwrap();
+        let day = NaiveDate::from_ymd_opt(2025, 1, 1).unwrap();
+        let before = WordGuess::new(day, MAXIMUM_GUESSES);
+
+        // A guess that was dropped from the word list since, scored against another answer
+        let result = score("zzzzz", "fuzzy");
+        let after = before.clone().restore("zzzzz".to_string(), result.clone());
+        assert!(before.guess("zzzzz").is_err());
+        record_guess(&conn, &user, &before, 0, &"zzzzz".to_string(), &after).unwrap();
+
+        // The game still loads, with the feedback it got
+        match get_game::<WordGuess>(&conn, &user.stale_token, day).unwrap() {
+            Some((word_guess, _)) => {
+                assert_eq!(json!(word_guess.guesses), json!([result]));
+                assert_eq!(json!(word_guess), json!(after));
+            }
+            other => panic!("Expected a WordGuess, got {:?}", other),
+        }
+    }
+
     #[test]
     fn test_get_all_users() {
         let conn = setup_test_db();
@@ -339,12 +840,8 @@ mod tests {
         let conn = setup_test_db();
 
         // Insert a user
-        let user = get_user(&conn, UserRequest::NewUser).unwrap();
-        let token = update_user_game_status(
-            &conn,
-            &user.stale_token,
-            &GameStatus::WordGuess(WordGuess::new())
-        ).unwrap();
+        let mut user = get_user(&conn, UserRequest::NewUser).unwrap();
+        let token = guess(&conn, &mut user, Local::now().date_naive(), "crane");
 
         // Verify user exists
         let user_in_db = get_user(
@@ -363,5 +860,47 @@ mod tests {
         // Verify the database is empty
         let users = get_all_users(&conn).unwrap();
         assert_eq!(users.len(), 0);
+        assert!(get_guess_history(&conn, &user.stale_token).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_concurrent_updates() {
+        let path = std::env::temp_dir().join(format!("word_games_{}.sqlite", create_token()));
+        let pool = initialize_pool(path.to_str(), 8).unwrap();
+
+        let journal_mode: String = pool
+            .get()
+            .unwrap()
+            .query_row("PRAGMA journal_mode", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(journal_mode, "wal");
+
+        // Parallel players must not fail with `database is locked`
+        let workers: Vec<_> = (0..8)
+            .map(|_| {
+                let pool = pool.clone();
+                std::thread::spawn(move || {
+                    let conn = pool.get().unwrap();
+                    let mut user = get_user(&conn, UserRequest::NewUser).unwrap();
+                    for days in 0..20 {
+                        // A new game every time, replaying the same game would conflict
+                        let day = Local::now().date_naive() - Duration::days(days);
+                        let game = WordGuess::new(day, MAXIMUM_GUESSES);
+                        let fresh_token = record_guess(&conn, &user, &game, 0, &"crane".to_string(), &game).unwrap();
+                        user.fresh_token = Some(fresh_token);
+                    }
+                })
+            })
+            .collect();
+        for worker in workers {
+            worker.join().unwrap();
+        }
+
+        assert_eq!(get_all_users(&pool.get().unwrap()).unwrap().len(), 8);
+
+        drop(pool);
+        for suffix in ["", "-wal", "-shm"] {
+            let _ = std::fs::remove_file(format!("{}{}", path.display(), suffix));
+        }
     }
 }
@@ -0,0 +1,231 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fs, io,
+    ops::RangeInclusive,
+    path::Path,
+    sync::OnceLock,
+};
+
+use lazy_static::lazy_static;
+
+static BUNDLED_ANSWERS: &str = include_str!("../words/answers.txt");
+static BUNDLED_ALLOWED: &str = include_str!("../words/allowed.txt");
+static BUNDLED_OTHER_LENGTHS: &str = include_str!("../words/american-english.txt");
+
+/// The length of the words in `dictionary()`
+pub static LETTERS: usize = 5;
+/// The word lengths with a dictionary
+pub static WORD_LENGTHS: RangeInclusive<usize> = 4..=11;
+
+lazy_static! {
+    static ref BUNDLED: Dictionary = Dictionary::parse(BUNDLED_ANSWERS, BUNDLED_ALLOWED);
+    /// The bundled words of every other length.\
+    /// Plurals and inflected verbs are accepted guesses, but never the answer
+    static ref OTHER_LENGTHS: HashMap<usize, Dictionary> = {
+        let words: Vec<&str> = BUNDLED_OTHER_LENGTHS.lines().filter(|line| !line.starts_with('#')).collect();
+        let stems: HashSet<&str> = words
+            .iter()
+            .copied()
+            .chain(BUNDLED.allowed().map(String::as_str))
+            .collect();
+
+        // The answers and the allowed guesses of every length
+        let mut lists: HashMap<usize, (String, String)> = HashMap::new();
+        for word in words {
+            let (answers, allowed) = lists.entry(word.chars().count()).or_default();
+            let list = if is_inflection(word, &stems) { allowed } else { answers };
+            list.push_str(word);
+            list.push('\n');
+        }
+        lists
+            .into_iter()
+            .map(|(letters, (answers, allowed))| (letters, Dictionary::parse(&answers, &allowed)))
+            .collect()
+    };
+}
+
+/// Whether `word` looks like a plural, an inflected verb or a comparative, ex. `abacuses`, `abased`, `abasing` or `ablest`
+/// ### Arguments
+/// * `word` - A lowercase word
+/// * `stems` - The words an inflection can come from
+///
+/// Every word ending in `s` counts as a plural, except for `-ss`, `-us` and `-is` endings like `status` or `basis`.\
+/// `-ed`, `-ing` and `-est` forms only count when their stem is a word, ex. `baked` from `bake` but not `bleed`.
+/// An `-er` form only counts next to its `-est` form, so `abuser` stays
+fn is_inflection(word: &str, stems: &HashSet<&str>) -> bool {
+    if word.ends_with('s') {
+        return !["ss", "us", "is"].iter().any(|ending| word.ends_with(ending));
+    }
+    if let Some(stem) = word.strip_suffix("er") {
+        return stems.contains(format!("{}est", stem).as_str());
+    }
+
+    let Some(stem) = ["ed", "ing", "est"].iter().find_map(|suffix| word.strip_suffix(suffix)) else {
+        return false;
+    };
+    let mut candidates = vec![stem.to_string(), format!("{}e", stem)];
+    // `stopped` from `stop`, `carried` from `carry`
+    let mut letters = stem.chars().rev();
+    if let (Some(last), Some(previous)) = (letters.next(), letters.next()) {
+        if last == previous {
+            candidates.push(stem[..stem.len() - last.len_utf8()].to_string());
+        }
+        if last == 'i' {
+            candidates.push(format!("{}y", &stem[..stem.len() - 1]));
+        }
+    }
+    candidates.iter().any(|candidate| stems.contains(candidate.as_str()))
+}
+
+/// A custom dictionary loaded at startup with `load`
+static CUSTOM: OnceLock<Dictionary> = OnceLock::new();
+
+/// The words a `WordGuess` game can use\
+/// `answers`: Words that can be picked as the answer\
+/// `allowed`: Every word that is accepted as a guess, answers included
+#[derive(Debug)]
+pub struct Dictionary {
+    answers: Vec<String>,
+    allowed: HashSet<String>,
+}
+
+impl Dictionary {
+    /// Build a dictionary from newline separated word lists
+    /// ### Arguments
+    /// * `answers` - Words that can be picked as the answer
+    /// * `allowed` - Extra words that are only accepted as guesses
+    ///
+    /// Blank lines and lines starting with `#` are ignored
+    pub fn parse(answers: &str, allowed: &str) -> Self {
+        fn words(list: &str) -> impl Iterator<Item = String> + '_ {
+            list.lines()
+                .map(|line| line.trim().to_lowercase())
+                .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        }
+
+        let mut seen = HashSet::new();
+        let answers: Vec<String> = words(answers)
+            .filter(|word| seen.insert(word.clone()))
+            .collect();
+
+        let mut allowed: HashSet<String> = words(allowed).collect();
+        allowed.extend(answers.iter().cloned());
+
+        Dictionary { answers, allowed }
+    }
+
+    /// Read a dictionary from word list files
+    /// ### Arguments
+    /// * `allowed` - The list of accepted guesses
+    /// * `answers` - The list of possible answers. When missing, every allowed word can be the answer
+    pub fn from_files(allowed: &Path, answers: Option<&Path>) -> io::Result<Self> {
+        let allowed = fs::read_to_string(allowed)?;
+        match answers {
+            Some(answers) => Ok(Dictionary::parse(&fs::read_to_string(answers)?, &allowed)),
+            None => Ok(Dictionary::parse(&allowed, "")),
+        }
+    }
+
+    /// Whether `word` is accepted as a guess
+    pub fn is_allowed(&self, word: &str) -> bool {
+        self.allowed.contains(word)
+    }
+
+    /// Every accepted guess, in no particular order
+    pub fn allowed(&self) -> impl Iterator<Item = &String> {
+        self.allowed.iter()
+    }
+
+    /// The possible answers, in list order
+    pub fn answers(&self) -> &[String] {
+        &self.answers
+    }
+}
+
+/// The dictionary used by the games.\
+/// The custom dictionary when one was loaded, otherwise the bundled word lists
+pub fn dictionary() -> &'static Dictionary {
+    CUSTOM.get().unwrap_or(&BUNDLED)
+}
+
+/// The dictionary of words with `letters` letters.\
+/// `dictionary()` for 5 letters, otherwise the bundled list of that length
+/// ### Arguments
+/// * `letters` - The length of the words, in `WORD_LENGTHS`
+pub fn dictionary_of(letters: usize) -> Option<&'static Dictionary> {
+    if letters == LETTERS {
+        return Some(dictionary());
+    }
+    OTHER_LENGTHS.get(&letters).filter(|_| WORD_LENGTHS.contains(&letters))
+}
+
+/// Replace the bundled 5 letter word lists with custom files
+/// ### Errors
+/// Returns an error if a file can't be read, the allowed list is empty, or a dictionary was already loaded
+pub fn load(allowed: &Path, answers: Option<&Path>) -> io::Result<()> {
+    let custom = Dictionary::from_files(allowed, answers)?;
+    if custom.answers.is_empty() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "The word list doesn't contain any answers",
+        ));
+    }
+
+    CUSTOM
+        .set(custom)
+        .map_err(|_| io::Error::new(io::ErrorKind::AlreadyExists, "A word list was already loaded"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bundled_lists() {
+        let dictionary = dictionary();
+        assert_eq!(dictionary.answers().len(), 2315);
+        assert!(dictionary.answers().iter().all(|word| word.chars().count() == 5));
+        assert!(dictionary.answers().iter().all(|word| dictionary.is_allowed(word)));
+        assert!(dictionary.is_allowed("eerie"));
+        assert!(!dictionary.is_allowed("zzzzz"));
+    }
+
+    #[test]
+    fn test_every_length() {
+        for letters in WORD_LENGTHS.clone() {
+            let dictionary = dictionary_of(letters).unwrap();
+            assert!(!dictionary.answers().is_empty(), "no {} letter answers", letters);
+            assert!(dictionary.answers().iter().all(|word| word.chars().count() == letters));
+        }
+        assert!(dictionary_of(7).unwrap().is_allowed("example"));
+        assert!(dictionary_of(3).is_none());
+
+        // Plurals and inflected verbs are guesses, but never answers
+        for (word, answer) in [("abacus", true), ("abacuses", false), ("abases", false), ("abased", false), ("abashing", false)] {
+            let dictionary = dictionary_of(word.len()).unwrap();
+            assert!(dictionary.is_allowed(word), "{} is not allowed", word);
+            assert_eq!(dictionary.answers().contains(&word.to_string()), answer, "{}", word);
+        }
+        assert!(dictionary_of(12).is_none());
+    }
+
+    #[test]
+    fn test_is_inflection() {
+        let stems: HashSet<&str> = ["bake", "stop", "carry", "jump", "box", "able", "achy", "achiest"].into_iter().collect();
+        for word in ["boxes", "cats", "baked", "baking", "stopped", "carried", "jumping", "ablest", "achier"] {
+            assert!(is_inflection(word, &stems), "{}", word);
+        }
+        for word in ["status", "basis", "glass", "bleed", "string", "abacus", "abuser", "forest"] {
+            assert!(!is_inflection(word, &stems), "{}", word);
+        }
+    }
+
+    #[test]
+    fn test_parse() {
+        let dictionary = Dictionary::parse("# answers\nCrane\n\nslate\ncrane\n", "aahed\n");
+        assert_eq!(dictionary.answers(), ["crane", "slate"]);
+        assert!(dictionary.is_allowed("aahed"));
+        assert!(dictionary.is_allowed("crane"));
+        assert!(!dictionary.is_allowed("# answers"));
+    }
+}
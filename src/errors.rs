@@ -1,14 +1,51 @@
+use actix_web::{http::StatusCode, HttpResponse, ResponseError};
+use schemars::JsonSchema;
+use serde::Serialize;
+use serde_json::{json, Value};
 use thiserror::Error;
 
 /*
     DATABASE ERRORS
 */
 #[derive(Error, Debug)]
+#[allow(clippy::enum_variant_names)]
 pub enum DatabaseError {
     #[error("There's an issue with the request: {0}")]
     FromSQLError(#[from] rusqlite::Error),
     #[error("Unable to parse game status: {0}")]
-    GameStatusParseError(String)
+    GameStatusParseError(String),
+    #[error("The database is unavailable: {0}")]
+    PoolError(String),
+    #[error("Unable to migrate the database: {0}")]
+    MigrationError(String),
+    #[error(transparent)]
+    SessionError(#[from] SessionError),
+    #[error("Another guess was saved at the same time, get the game and try again")]
+    GuessConflict
+}
+
+impl From<r2d2::Error> for DatabaseError {
+    fn from(e: r2d2::Error) -> Self {
+        DatabaseError::PoolError(e.to_string())
+    }
+}
+
+/*
+    SESSION ERRORS
+*/
+
+#[derive(Error, Debug, PartialEq)]
+pub enum SessionError {
+    #[error("The {0} token is missing")]
+    MissingToken(&'static str),
+    #[error("The Authorization header is malformed, expected `Bearer <stale_token>.<fresh_token>`")]
+    MalformedToken,
+    #[error("There is no player with this stale token")]
+    UnknownSession,
+    #[error("The fresh token doesn't match the session")]
+    InvalidToken,
+    #[error("The fresh token was already replaced, the session may have been stolen and was revoked")]
+    TokenReplayed
 }
 
 /*
@@ -23,6 +60,280 @@ pub enum GameError {
     GameOver,
     #[error("{0}")]
     InvalidGuess(String),
+    #[error("`{0}` is not in the word list")]
+    NotInWordList(String),
+    #[error("`{0}` is not one of the words on the board")]
+    NotOnBoard(String),
+    #[error("You have to guess {expected} different words, not {got}")]
+    WrongWordCount { expected: usize, got: usize },
+    #[error("Guess already made.")]
+    DuplicateGuess,
     #[error("Network error: {0}")]
-    NetworkError(String)
-}
\ No newline at end of file
+    NetworkError(String),
+    #[error("Today's puzzle is unavailable: {0}")]
+    PuzzleUnavailable(String),
+    #[error("Unable to read the puzzle page: {0}")]
+    PuzzleParseError(String),
+    #[error("{0}")]
+    VariantMismatch(String),
+    #[error("{0}")]
+    InvalidVariant(String)
+}
+
+/*
+    CONFIG ERRORS
+*/
+
+#[derive(Error, Debug)]
+pub enum ConfigError {
+    #[error("Unknown setting: {0}")]
+    UnknownSetting(String),
+    #[error("Missing a value for --{0}")]
+    MissingValue(String),
+    #[error("Invalid value `{value}` for {key}: {reason}")]
+    InvalidValue {
+        key: String,
+        value: String,
+        reason: String,
+    },
+    #[error("Unable to read the config file {0}")]
+    FileError(String)
+}
+
+/*
+    API ERRORS
+*/
+
+/// Every error an endpoint answers with.\
+/// Sent as an `ErrorBody` so clients can branch on its `code` instead of the message
+#[derive(Error, Debug)]
+pub enum ApiError {
+    #[error("Invalid request: {0}")]
+    InvalidRequest(String),
+    #[error("There is no game called `{0}`")]
+    UnknownGame(String),
+    #[error("There is no `{0}` schema, expected `request`, `response`, `state` or `error`")]
+    UnknownSchema(String),
+    #[error(transparent)]
+    Game(#[from] GameError),
+    #[error(transparent)]
+    Database(#[from] DatabaseError),
+    #[error("Something went wrong: {0}")]
+    Internal(String),
+}
+
+impl From<SessionError> for ApiError {
+    fn from(e: SessionError) -> Self {
+        ApiError::Database(DatabaseError::SessionError(e))
+    }
+}
+
+/// The JSON body of every error response, ex.
+/// ```json
+/// {"code": "not_in_word_list", "message": "`qwert` is not in the word list", "details": {"word": "qwert"}}
+/// ```
+#[derive(Serialize, Debug, PartialEq, JsonSchema)]
+pub struct ErrorBody {
+    /// A stable, machine-readable code
+    pub code: &'static str,
+    /// A message that can be shown to the player
+    pub message: String,
+    /// More about the error, depending on the code
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub details: Option<Value>,
+}
+
+impl ApiError {
+    /// The machine-readable code of the error
+    pub fn code(&self) -> &'static str {
+        match self {
+            ApiError::InvalidRequest(_) => "invalid_request",
+            ApiError::UnknownGame(_) => "unknown_game",
+            ApiError::UnknownSchema(_) => "unknown_schema",
+            ApiError::Internal(_) => "internal_error",
+            ApiError::Game(e) => match e {
+                GameError::MaximumGuesses => "maximum_guesses",
+                GameError::GameOver => "game_over",
+                GameError::InvalidGuess(_) => "invalid_guess",
+                GameError::NotInWordList(_) => "not_in_word_list",
+                GameError::NotOnBoard(_) => "not_on_board",
+                GameError::WrongWordCount { .. } => "wrong_word_count",
+                GameError::DuplicateGuess => "duplicate_guess",
+                GameError::NetworkError(_) => "network_error",
+                GameError::PuzzleUnavailable(_) => "puzzle_unavailable",
+                GameError::PuzzleParseError(_) => "puzzle_parse_error",
+                GameError::VariantMismatch(_) => "variant_mismatch",
+                GameError::InvalidVariant(_) => "invalid_variant",
+            },
+            ApiError::Database(e) => match e {
+                DatabaseError::FromSQLError(_) | DatabaseError::MigrationError(_) => "database_error",
+                DatabaseError::GameStatusParseError(_) => "corrupted_game",
+                DatabaseError::PoolError(_) => "database_unavailable",
+                DatabaseError::GuessConflict => "guess_conflict",
+                DatabaseError::SessionError(e) => match e {
+                    SessionError::MissingToken(_) => "missing_token",
+                    SessionError::MalformedToken => "malformed_token",
+                    SessionError::UnknownSession => "unknown_session",
+                    SessionError::InvalidToken => "invalid_token",
+                    SessionError::TokenReplayed => "token_replayed",
+                },
+            },
+        }
+    }
+
+    /// The body sent to the client.\
+    /// Internal errors are only logged, the player gets a generic message
+    pub fn body(&self) -> ErrorBody {
+        let message = match self {
+            ApiError::Database(DatabaseError::FromSQLError(_) | DatabaseError::MigrationError(_)) => {
+                "Something went wrong with the database, try again later".to_string()
+            }
+            ApiError::Database(DatabaseError::GameStatusParseError(_)) => {
+                "Your game has been corrupted. Please wait for tomorrow".to_string()
+            }
+            ApiError::Internal(_) => "Something went wrong, try again later".to_string(),
+            e => e.to_string(),
+        };
+
+        let details = match self {
+            ApiError::InvalidRequest(reason) => Some(json!({ "reason": reason })),
+            ApiError::UnknownGame(game) => Some(json!({ "game": game })),
+            ApiError::UnknownSchema(kind) => Some(json!({ "kind": kind })),
+            ApiError::Game(GameError::InvalidGuess(reason) | GameError::InvalidVariant(reason)) => {
+                Some(json!({ "reason": reason }))
+            }
+            ApiError::Game(GameError::NotInWordList(word) | GameError::NotOnBoard(word)) => Some(json!({ "word": word })),
+            ApiError::Game(GameError::WrongWordCount { expected, got }) => {
+                Some(json!({ "expected": expected, "got": got }))
+            }
+            ApiError::Database(DatabaseError::SessionError(SessionError::MissingToken(token))) => {
+                Some(json!({ "token": format!("{}_token", token) }))
+            }
+            _ => None,
+        };
+
+        ErrorBody {
+            code: self.code(),
+            message,
+            details,
+        }
+    }
+}
+
+impl ResponseError for ApiError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            ApiError::InvalidRequest(_) => StatusCode::BAD_REQUEST,
+            ApiError::UnknownGame(_) | ApiError::UnknownSchema(_) => StatusCode::NOT_FOUND,
+            ApiError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            ApiError::Game(e) => match e {
+                GameError::InvalidVariant(_) => StatusCode::BAD_REQUEST,
+                GameError::MaximumGuesses | GameError::GameOver | GameError::VariantMismatch(_) => {
+                    StatusCode::CONFLICT
+                }
+                GameError::InvalidGuess(_)
+                | GameError::NotInWordList(_)
+                | GameError::NotOnBoard(_)
+                | GameError::WrongWordCount { .. }
+                | GameError::DuplicateGuess => StatusCode::UNPROCESSABLE_ENTITY,
+                GameError::NetworkError(_) | GameError::PuzzleParseError(_) => StatusCode::BAD_GATEWAY,
+                GameError::PuzzleUnavailable(_) => StatusCode::SERVICE_UNAVAILABLE,
+            },
+            ApiError::Database(e) => match e {
+                DatabaseError::FromSQLError(_)
+                | DatabaseError::GameStatusParseError(_)
+                | DatabaseError::MigrationError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+                DatabaseError::PoolError(_) => StatusCode::SERVICE_UNAVAILABLE,
+                DatabaseError::SessionError(_) => StatusCode::UNAUTHORIZED,
+                DatabaseError::GuessConflict => StatusCode::CONFLICT,
+            },
+        }
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        let status = self.status_code();
+        if status.is_server_error() {
+            eprintln!("{} {}: {}", status.as_u16(), self.code(), self);
+        }
+        let mut response = HttpResponse::build(status).json(self.body());
+        if let ApiError::Database(DatabaseError::SessionError(
+            SessionError::UnknownSession | SessionError::InvalidToken | SessionError::TokenReplayed,
+        )) = self
+        {
+            crate::session::clear_session(&mut response);
+        }
+        response
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_status_codes() {
+        let cases: Vec<(ApiError, StatusCode, &str)> = vec![
+            (ApiError::InvalidRequest("missing field `guess`".to_string()), StatusCode::BAD_REQUEST, "invalid_request"),
+            (GameError::InvalidVariant("Words have 4 to 11 letters".to_string()).into(), StatusCode::BAD_REQUEST, "invalid_variant"),
+            (GameError::GameOver.into(), StatusCode::CONFLICT, "game_over"),
+            (GameError::NotInWordList("qwert".to_string()).into(), StatusCode::UNPROCESSABLE_ENTITY, "not_in_word_list"),
+            (GameError::NotOnBoard("cake".to_string()).into(), StatusCode::UNPROCESSABLE_ENTITY, "not_on_board"),
+            (GameError::WrongWordCount { expected: 4, got: 3 }.into(), StatusCode::UNPROCESSABLE_ENTITY, "wrong_word_count"),
+            (GameError::DuplicateGuess.into(), StatusCode::UNPROCESSABLE_ENTITY, "duplicate_guess"),
+            (GameError::PuzzleUnavailable("Not found".to_string()).into(), StatusCode::SERVICE_UNAVAILABLE, "puzzle_unavailable"),
+            (DatabaseError::PoolError("Timed out".to_string()).into(), StatusCode::SERVICE_UNAVAILABLE, "database_unavailable"),
+            (DatabaseError::GuessConflict.into(), StatusCode::CONFLICT, "guess_conflict"),
+            (SessionError::TokenReplayed.into(), StatusCode::UNAUTHORIZED, "token_replayed"),
+            (ApiError::Internal("task panicked".to_string()), StatusCode::INTERNAL_SERVER_ERROR, "internal_error"),
+        ];
+
+        for (error, status, code) in cases {
+            assert_eq!(error.status_code(), status);
+            assert_eq!(error.code(), code);
+        }
+    }
+
+    #[test]
+    fn test_session_cookies_cleared() {
+        let removed = |error: ApiError| {
+            let response = error.error_response();
+            let mut names: Vec<String> = response
+                .cookies()
+                .filter(|cookie| cookie.max_age() == Some(actix_web::cookie::time::Duration::ZERO))
+                .map(|cookie| format!("{}={}", cookie.name(), cookie.path().unwrap_or_default()))
+                .collect();
+            names.sort();
+            names
+        };
+
+        // A session that is no longer accepted starts over as a new player
+        for error in [SessionError::UnknownSession, SessionError::InvalidToken, SessionError::TokenReplayed] {
+            assert_eq!(removed(error.into()), ["fresh_token=/", "stale_token=/"]);
+        }
+        assert!(removed(SessionError::MalformedToken.into()).is_empty());
+        assert!(removed(GameError::GameOver.into()).is_empty());
+    }
+
+    #[test]
+    fn test_error_body() {
+        let body = ApiError::from(GameError::NotInWordList("qwert".to_string())).body();
+        assert_eq!(
+            json!(body),
+            json!({
+                "code": "not_in_word_list",
+                "message": "`qwert` is not in the word list",
+                "details": { "word": "qwert" },
+            })
+        );
+
+        // Database internals stay in the logs
+        let body = ApiError::from(DatabaseError::MigrationError("no such table: users".to_string())).body();
+        assert_eq!(body.code, "database_error");
+        assert!(!body.message.contains("users"));
+        assert!(body.details.is_none());
+
+        let body = ApiError::Internal("task panicked".to_string()).body();
+        assert_eq!(body.code, "internal_error");
+        assert!(!body.message.contains("panicked"));
+    }
+}
@@ -1,3 +1,22 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// Where a game stands after the latest guess
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default, JsonSchema)]
+pub enum Outcome {
+    #[default]
+    InProgress,
+    Won,
+    Lost,
+}
+
+impl Outcome {
+    /// Whether the game has finished, won or lost
+    pub fn is_over(&self) -> bool {
+        *self != Outcome::InProgress
+    }
+}
+
 /// The main trait for a `Game`
 /// ### Type Parameters
 /// * `T` - The type of the guess. Usually a primitive type like `u8` or `String`
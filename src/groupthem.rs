@@ -1,125 +1,164 @@
-use chrono::{Duration, Local};
-use regex::Regex;
-use rusqlite::Connection;
+use chrono::{Duration, NaiveDate};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
-use std::{collections::{hash_map::DefaultHasher, HashSet}, hash::{Hash, Hasher}};
+use std::collections::HashSet;
 use crate::{
-    db, errors::GameError, game::Game, models::{mix_colors, Group, GroupResult, Ranking, Word}
+    errors::GameError, game::{Game, Outcome}, models::{ApproxColor, GroupFeedback, GroupResult, GroupThemRequest, SolvedGroup, Word},
+    registry::{GameContext, GameFuture, GameMetadata, NoOptions}
 };
-use html_escape::decode_html_entities;
 
-static MAXIMUM_BAD_GUESSES: u8 = 4;
-static GROUPS: u8 = 4;
-static ITEMS_PER_GROUP: usize = 4;
+pub static MAXIMUM_BAD_GUESSES: u8 = 4;
+pub static GROUPS: u8 = 4;
+pub static ITEMS_PER_GROUP: usize = 4;
 
 /// Represents the game state for the user
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, JsonSchema)]
 pub struct GroupThem {
     pub guesses: Vec<GroupResult>,
     pub available_words: Vec<Word>,
+    /// Games stored before it was configurable default to `MAXIMUM_BAD_GUESSES`
+    #[serde(default = "default_maximum_bad_guesses")]
+    pub maximum_bad_guesses: u8,
+    /// The day the puzzle is served. Games stored before it was recorded default to 1970-01-01
+    #[serde(default)]
+    pub day: NaiveDate,
+    #[serde(default)]
+    pub outcome: Outcome,
+    /// Every group with its words, revealed once the game is over
+    #[serde(default)]
+    pub solution: Option<Vec<SolvedGroup>>,
 }
 
+fn default_maximum_bad_guesses() -> u8 {
+    MAXIMUM_BAD_GUESSES
+}
 
-pub async fn get_data(conn: &Connection, date_offset: usize) -> Result<(Vec<Group>, Vec<Word>), GameError> {
+/// The state of a GroupThem game as shown to players.\
+/// Unlike `GroupThem` it never exposes the group of an unsolved word
+#[derive(Debug, Serialize, Deserialize, Clone, JsonSchema)]
+pub struct GroupThemView {
+    pub available_words: Vec<String>,
+    /// The groups that were found so far
+    pub solved: Vec<SolvedGroup>,
+    pub guesses: Vec<GroupResultView>,
+    pub day: NaiveDate,
+    pub outcome: Outcome,
+    pub solution: Option<Vec<SolvedGroup>>,
+}
 
-    if let Ok(cache) = db::get_game_cache::<(Vec<Group>, Vec<Word>)>(&conn, date_offset) {
-        return Ok(cache)
-    }
-    println!("*****************************************");
-    println!("Hey! You are using a third-party service to get the game state. This service could not be possible without the operation and maintenance of https://www.connections-answer.com . Please check them out and buy the author a coffee if this helps!");
-    println!("*****************************************");
+/// A guess as shown to players
+#[derive(Debug, Serialize, Deserialize, Clone, JsonSchema)]
+pub struct GroupResultView {
+    pub words: Vec<String>,
+    /// The color of the group, only sent for correct guesses.\
+    /// The mixed color of a wrong guess would tell which of its words belong together
+    pub color: Option<ApproxColor>,
+    pub correct: bool,
+    pub feedback: GroupFeedback,
+}
 
-    fn calculate_hash<T: Hash>(t: &T) -> u64 {
-        let mut hasher = DefaultHasher::new();
-        t.hash(&mut hasher);
-        hasher.finish()
+impl From<&GroupThem> for GroupThemView {
+    fn from(state: &GroupThem) -> Self {
+        GroupThemView {
+            available_words: state
+                .available_words
+                .iter()
+                .map(|w| w.text.clone())
+                .collect(),
+            solved: state
+                .guesses
+                .iter()
+                .filter(|g| g.is_group())
+                .map(|g| SolvedGroup {
+                    group: g.words[0].group.clone(),
+                    words: g.words.iter().map(|w| w.text.clone()).collect(),
+                })
+                .collect(),
+            guesses: state
+                .guesses
+                .iter()
+                .map(|g| GroupResultView {
+                    words: g.words.iter().map(|w| w.text.clone()).collect(),
+                    color: g.is_group().then(|| g.color.clone()),
+                    correct: g.is_group(),
+                    feedback: g.feedback.clone(),
+                })
+                .collect(),
+            day: state.day,
+            outcome: state.outcome,
+            solution: state.solution.clone(),
+        }
     }
+}
 
-    fn remove_unwanted_characters(input: &str) -> String {
-        // Get rid of dem pesky emojis
-        input.chars()
-            .filter(|&c| c.is_ascii() || (c as u32) <= 0xFFFF)
-            .collect()
-        
+impl GroupThem {
+    /// Start a board of `words`
+    /// ### Arguments
+    /// * `words` - The words of every group
+    /// * `day` - The day of the puzzle
+    /// * `maximum_bad_guesses` - The number of wrong guesses before the game is lost
+    pub fn new(words: &[Word], day: NaiveDate, maximum_bad_guesses: u8) -> Self {
+        GroupThem {
+            guesses: Vec::new(),
+            available_words: words.to_vec(),
+            maximum_bad_guesses,
+            day,
+            outcome: Outcome::InProgress,
+            solution: None,
+        }
     }
 
-    let date = Local::now() - Duration::days(
-        date_offset.min(365).max(0) as i64
-    );
-    let date_formatted = date.format("%B-%e-%Y").to_string().to_lowercase().replace("- ", "-").trim().to_string();
-    
-    let url = format!("https://www.connections-answer.com/posts/nyt-connections-answer-hint-{}", date_formatted);
-
-    println!("{:?}", url);
-
-    let response = reqwest::get(&url)
-    .await
-    .map_err(
-        |e| GameError::NetworkError(e.to_string())
-    )?
-    .text()
-    .await
-    .map_err(
-        |e| GameError::NetworkError(e.to_string())
-    )?;
-    // Set text to <h2>
-    let sliced = response.split("<h2 id=what-is-the-answer-to-connections-today>").collect::<Vec<&str>>()[1];
-
-    // Get the first <ul> element and extract all content in-between
-    let start = sliced.find("<ul>").unwrap();
-    let end = sliced.find("</ul>").unwrap();
-
-    let sliced = &sliced[start..end];
-
-    // Extract all text found inside <font> elements
-    let mut words = Vec::new();
-    let mut groups = Vec::new();
-    let re = Regex::new(r"<font[^>]*>(.*?)</font>").unwrap();
-
-    println!("{:?}", sliced);
-
-    for (i, caps) in re.captures_iter(sliced).enumerate() {
-        if let Some(matched) = caps.get(1) {
-            let cleaned_text = remove_unwanted_characters(matched.as_str());
-
-            groups.push(Group {
-                name: "Unknown".to_string(), // The site author changed the structure
-                ranking: Ranking::from_index(i),
-            });
-
-            let group_words = cleaned_text
-                .split(",")
-                .map(|w| Word{
-                    text: decode_html_entities(w.trim().to_lowercase().as_str()).to_string(),
-                    group: groups[i].clone()
-                });
-            
-            println!("{:?}", group_words);
-            words.extend(group_words);
+    /// Determine the outcome from the guesses made so far
+    fn evaluate(&self) -> Outcome {
+        if self.count_good_guesses() == GROUPS {
+            Outcome::Won
+        } else if self.count_bad_guesses() >= self.maximum_bad_guesses {
+            Outcome::Lost
+        } else {
+            Outcome::InProgress
         }
     }
 
-    words.sort_by(|a, b| calculate_hash(a).cmp(&calculate_hash(b)));
-
-    let _ = db::store_game_cache::<(Vec<Group>, Vec<Word>)>(
-        conn,
-        &(groups.clone(), words.clone()),
-        date_offset
-    ).map_err(
-        |e| {
-            eprintln!("Failed to save game to cache: {}", e.to_string())
+    /// Every group with its words, ordered by ranking
+    fn reveal(&self) -> Vec<SolvedGroup> {
+        let solved = self
+            .guesses
+            .iter()
+            .filter(|g| g.is_group())
+            .flat_map(|g| g.words.iter());
+
+        let mut groups: Vec<SolvedGroup> = Vec::new();
+        for word in solved.chain(self.available_words.iter()) {
+            match groups.iter_mut().find(|g| g.group == word.group) {
+                Some(group) => group.words.push(word.text.clone()),
+                None => groups.push(SolvedGroup {
+                    group: word.group.clone(),
+                    words: vec![word.text.clone()],
+                }),
+            }
         }
-    );
 
-    Ok((groups, words))
+        groups.sort_by(|a, b| a.group.ranking.cmp(&b.group.ranking));
+        groups
+    }
 
-}
+    /// The board as it was before the first guess, with the solved words put back.\
+    /// Used to rebuild a game by replaying its guesses
+    pub fn restart(&self) -> Self {
+        let mut available_words: Vec<Word> = self
+            .guesses
+            .iter()
+            .filter(|g| g.is_group())
+            .flat_map(|g| g.words.iter().cloned())
+            .collect();
+        available_words.extend(self.available_words.iter().cloned());
 
-impl GroupThem {
-    pub fn new(words: &[Word]) -> Self {
         GroupThem {
             guesses: Vec::new(),
-            available_words: words.to_vec(),
+            available_words,
+            outcome: Outcome::InProgress,
+            solution: None,
+            ..self.clone()
         }
     }
 
@@ -137,6 +176,41 @@ impl GroupThem {
             .count() as u8
     }
 
+    /// The words left on the board after `guess`, a correct guess takes its words off
+    fn remaining(&self, guess: &[Word]) -> Vec<Word> {
+        if guess.iter().all(|w| w.group == guess[0].group) {
+            self.available_words
+                .iter()
+                .filter(|w| !guess.iter().any(|g| g.text == w.text))
+                .cloned()
+                .collect()
+        } else {
+            self.available_words.clone()
+        }
+    }
+
+    /// The state after `result`, with the words left on the board
+    fn record(&self, result: GroupResult, available_words: Vec<Word>) -> Self {
+        let mut state = GroupThem {
+            guesses: {
+                let mut new_guesses = self.guesses.clone();
+                new_guesses.push(result);
+                new_guesses
+            },
+            available_words,
+            maximum_bad_guesses: self.maximum_bad_guesses,
+            day: self.day,
+            outcome: Outcome::InProgress,
+            solution: None,
+        };
+
+        state.outcome = state.evaluate();
+        if state.outcome.is_over() {
+            state.solution = Some(state.reveal());
+        }
+        state
+    }
+
     fn good_guesses(&self) -> Vec<Vec<String>> {
         self.guesses
             .iter()
@@ -152,21 +226,7 @@ impl Game<Vec<String>, Vec<Word>> for GroupThem {
     type GameResult = Vec<Word>;
 
     fn process(&self, guess: Vec<Word>) -> Result<Self::GameResult, Self::GameError> {
-        if guess.iter().all(|w| w.group == guess[0].group) {
-            // Was a correct guess
-            // Remove the used words from the available words
-            let available_words: Vec<Word> = self
-                .available_words
-                .iter()
-                .filter(|w| !guess.iter().any(|g| g.text == w.text))
-                .cloned()
-                .collect();
-            Ok(available_words)
-        } else {
-            Ok(
-                self.available_words.clone()
-            )
-        }
+        Ok(self.remaining(&guess))
     }
 
     fn clean(&self, guess: Vec<String>) -> Result<Vec<Word>, Self::GameError> {
@@ -177,9 +237,10 @@ impl Game<Vec<String>, Vec<Word>> for GroupThem {
 
         // * There wasn't 4 guesses made
         if guess_set.len() != ITEMS_PER_GROUP {
-            Err(GameError::InvalidGuess(
-                "You have to guess 4 words".to_string(),
-            ))?
+            Err(GameError::WrongWordCount {
+                expected: ITEMS_PER_GROUP,
+                got: guess_set.len(),
+            })?
         }
 
         // * The game has already been won
@@ -188,7 +249,7 @@ impl Game<Vec<String>, Vec<Word>> for GroupThem {
         }
 
         // * Exceeded the maximum number of bad guesses
-        if self.count_bad_guesses() >= MAXIMUM_BAD_GUESSES {
+        if self.count_bad_guesses() >= self.maximum_bad_guesses {
             Err(GameError::MaximumGuesses)?
         }
 
@@ -217,10 +278,7 @@ impl Game<Vec<String>, Vec<Word>> for GroupThem {
         // * A word is not in the available words
         for word in &words {
             if !self.available_words.iter().any(|w| w.text == word.text) {
-                return Err(GameError::InvalidGuess(format!(
-                    "Word is not in the available words: {}",
-                    word.text
-                )));
+                return Err(GameError::NotOnBoard(word.text.clone()));
             }
         }
 
@@ -230,7 +288,7 @@ impl Game<Vec<String>, Vec<Word>> for GroupThem {
             .iter()
             .any(|g| g.words.iter().all(|w| words.iter().any(|word| word.text == w.text)))
         {
-            Err(GameError::InvalidGuess("Guess already made.".to_string()))?
+            Err(GameError::DuplicateGuess)?
         }
         
         Ok(words)
@@ -239,26 +297,73 @@ impl Game<Vec<String>, Vec<Word>> for GroupThem {
     fn guess(&self, guess: Vec<String>) -> Result<Self::State, Self::GameError> {
         let words = self.clean(guess)?;
         let available_words = self.process(words.clone())?;
+        Ok(self.record(GroupResult::new(words), available_words))
+    }
+}
 
-        Ok(GroupThem {
-            guesses: {
-                let mut new_guesses = self.guesses.clone();
-                new_guesses.push(GroupResult {
-                    color: mix_colors(&words),
-                    words
-                });
-                new_guesses
-            },
-            available_words,
+impl GameMetadata for GroupThem {
+    const ROUTE: &'static str = "groupthem";
+    const KEY: &'static str = "group_them";
+    const NAME: &'static str = "GroupThem";
+    const DESCRIPTION: &'static str =
+        "Find the groups of words that share a connection, four words at a time. Too many wrong guesses lose the game";
+
+    type Options = NoOptions;
+    type Request = GroupThemRequest;
+    type Guess = Vec<String>;
+    type Clean = Vec<Word>;
+    type Feedback = GroupResult;
+    type View = GroupThemView;
+    type Response = GroupThemView;
+
+    /// Serve the previous day's puzzle
+    fn start<'a>(context: &'a GameContext<'a>, day: NaiveDate, _options: &'a NoOptions) -> GameFuture<'a, Self> {
+        Box::pin(async move {
+            let (_, words) = context
+                .provider
+                .get_puzzle(context.pool, day - Duration::days(1))
+                .await?;
+            Ok(GroupThem::new(&words, day, context.config.maximum_bad_guesses))
         })
     }
+
+    fn into_guess(request: GroupThemRequest) -> Vec<String> {
+        request.guess
+    }
+
+    fn day(&self) -> NaiveDate {
+        self.day
+    }
+
+    fn last_result(&self) -> Option<GroupResult> {
+        self.guesses.last().cloned()
+    }
+
+    /// The stored result keeps the order of the words and the color the guess got
+    fn restore(self, _guess: Vec<String>, feedback: GroupResult) -> Self {
+        let available_words = self.remaining(&feedback.words);
+        self.record(feedback, available_words)
+    }
+
+    /// Only the public view is sent, the groups of unsolved words stay on the server
+    fn view(&self) -> GroupThemView {
+        GroupThemView::from(self)
+    }
+
+    fn guess_response(&self) -> GroupThemView {
+        self.view()
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::models::{Group, Ranking};
+    use crate::models::{GameStatus, Group, Ranking};
     use super::*;
 
+    fn day() -> NaiveDate {
+        NaiveDate::from_ymd_opt(2025, 1, 1).unwrap()
+    }
+
     fn setup() -> (Vec<Group>, Vec<Word>) {
         let groups = [
             Group {
@@ -349,43 +454,26 @@ mod tests {
         (groups.to_vec(), all_words.to_vec())
     }
 
-    #[test]
-    fn test_decode_html_entities() {
-        let result = decode_html_entities("&amp;").to_string();
-        let result_2 = decode_html_entities("pandora&rsquo;s box").to_string();
-        println!("{:?}", result);
-        println!("{:?}", result_2);
-        assert_eq!(result, "&".to_string());
-        assert_eq!(result_2, "pandora’s box".to_string());
-    }
-
-    #[tokio::test]
-    async fn test_get_words(){
-        let conn = db::initialize_connection(true);
-        let result = get_data(&conn, 1).await.unwrap();
-        println!("{:?}", result);
-    }
-
     #[test]
     fn test_try_bad_word() {
         let (_, all_words) = setup();
         let result = Word::try_from("sdfasfasdfasdfsadfasd", &all_words);
-        assert!(result.is_err());
+        assert!(matches!(result, Err(GameError::NotOnBoard(word)) if word == "sdfasfasdfasdfsadfasd"));
 
         let bad_words = vec![
             "sdfasfasdfasdfsadfasd".to_string(),
             "sdfasfasdfasdfsadfasd".to_string(),
         ];
-        let game = GroupThem::new(&all_words);
+        let game = GroupThem::new(&all_words, day(), MAXIMUM_BAD_GUESSES);
         let result = game.guess(bad_words);
 
-        assert!(result.is_err());
+        assert!(matches!(result, Err(GameError::WrongWordCount { expected: 4, got: 1 })));
     }
 
     #[test]
     fn test_guess_correct_group() {
         let (_, all_words) = setup();
-        let game = GroupThem::new(&all_words);
+        let game = GroupThem::new(&all_words, day(), MAXIMUM_BAD_GUESSES);
         let result = game.guess(vec![
             "cake".to_string(),
             "pie".to_string(),
@@ -405,7 +493,7 @@ mod tests {
     #[test]
     fn test_guess_incorrect_group() {
         let (_, all_words) = setup();
-        let game = GroupThem::new(&all_words);
+        let game = GroupThem::new(&all_words, day(), MAXIMUM_BAD_GUESSES);
         let result = game.guess(vec![
             "cake".to_string(),
             "pie".to_string(),
@@ -439,7 +527,7 @@ mod tests {
         ];
 
         let (_, all_words) = setup();
-        let mut game = GroupThem::new(&all_words);
+        let mut game = GroupThem::new(&all_words, day(), MAXIMUM_BAD_GUESSES);
         for guess in guesses.iter() {
             game.guesses.push(game.guess(guess.to_owned()).unwrap().guesses.last().unwrap().clone());
         }
@@ -457,7 +545,7 @@ mod tests {
     #[test]
     fn test_repeat_guess(){
         let (_, all_words) = setup();
-        let mut game = GroupThem::new(&all_words);
+        let mut game = GroupThem::new(&all_words, day(), MAXIMUM_BAD_GUESSES);
 
         let result = game.guess(vec![
             "cake".to_string(),
@@ -484,13 +572,13 @@ mod tests {
 
         println!("{:?}", result);
 
-        assert!(result.is_err());
+        assert!(matches!(result, Err(GameError::DuplicateGuess)));
     }
 
     #[test]
     fn test_ran_out_of_guesses() {
         let (_, all_words) = setup();
-        let mut game = GroupThem::new(&all_words);
+        let mut game = GroupThem::new(&all_words, day(), MAXIMUM_BAD_GUESSES);
 
         for i in 0..MAXIMUM_BAD_GUESSES {
             let result = game.guess(vec![
@@ -519,7 +607,7 @@ mod tests {
     #[test]
     fn test_guess_already_guessed_word() {
         let (_, all_words) = setup();
-        let game = GroupThem::new(&all_words);
+        let game = GroupThem::new(&all_words, day(), MAXIMUM_BAD_GUESSES);
         let result = game.guess(vec![
             "cake".to_string(),
             "pie".to_string(),
@@ -543,13 +631,13 @@ mod tests {
             "sphinx".to_string(),
         ]);
 
-        assert!(result.is_err());
+        assert!(matches!(result, Err(GameError::NotOnBoard(word)) if word == "cookie"));
     }
 
     #[test]
     fn test_game_over() {
         let (_, all_words) = setup();
-        let mut game = GroupThem::new(&all_words);
+        let mut game = GroupThem::new(&all_words, day(), MAXIMUM_BAD_GUESSES);
 
         for i in 0..GROUPS {
             let result = game.guess(vec![
@@ -574,4 +662,147 @@ mod tests {
 
         assert!(result.is_err());
     }
+
+    #[test]
+    fn test_one_away_feedback() {
+        let (_, all_words) = setup();
+        let game = GroupThem::new(&all_words, day(), MAXIMUM_BAD_GUESSES);
+
+        let game = game.guess(vec![
+            "cake".to_string(),
+            "pie".to_string(),
+            "pudding".to_string(),
+            "pen".to_string(),
+        ]).unwrap();
+        let feedback = &game.guesses.last().unwrap().feedback;
+        assert_eq!(feedback.most_common, 3);
+        assert!(feedback.one_away);
+
+        let game = game.guess(vec![
+            "cake".to_string(),
+            "pie".to_string(),
+            "pen".to_string(),
+            "notebook".to_string(),
+        ]).unwrap();
+        let feedback = &game.guesses.last().unwrap().feedback;
+        assert_eq!(feedback.most_common, 2);
+        assert!(!feedback.one_away);
+
+        let game = game.guess(vec![
+            "cake".to_string(),
+            "pie".to_string(),
+            "pudding".to_string(),
+            "cookie".to_string(),
+        ]).unwrap();
+        let feedback = &game.guesses.last().unwrap().feedback;
+        assert_eq!(feedback.most_common, 4);
+        assert!(!feedback.one_away);
+    }
+
+    #[test]
+    fn test_outcome_won_reveals_solution() {
+        let (groups, all_words) = setup();
+        let mut game = GroupThem::new(&all_words, day(), MAXIMUM_BAD_GUESSES);
+
+        for i in 0..GROUPS as usize {
+            assert_eq!(game.outcome, Outcome::InProgress);
+            assert!(game.solution.is_none());
+            game = game.guess(
+                all_words[i * ITEMS_PER_GROUP..(i + 1) * ITEMS_PER_GROUP]
+                    .iter()
+                    .map(|w| w.text.clone())
+                    .collect()
+            ).unwrap();
+        }
+
+        assert_eq!(game.outcome, Outcome::Won);
+        let solution = game.solution.unwrap();
+        assert_eq!(solution.len(), GROUPS as usize);
+        for (solved, group) in solution.iter().zip(groups.iter()) {
+            assert_eq!(&solved.group, group);
+            assert_eq!(solved.words.len(), ITEMS_PER_GROUP);
+        }
+    }
+
+    #[test]
+    fn test_outcome_lost_reveals_solution() {
+        let (_, all_words) = setup();
+        let mut game = GroupThem::new(&all_words, day(), MAXIMUM_BAD_GUESSES);
+
+        for i in 0..MAXIMUM_BAD_GUESSES as usize {
+            assert_eq!(game.outcome, Outcome::InProgress);
+            game = game.guess(vec![
+                all_words[0].text.clone(),
+                all_words[1].text.clone(),
+                all_words[2].text.clone(),
+                all_words[all_words.len() - 1 - i].text.clone(),
+            ]).unwrap();
+        }
+
+        assert_eq!(game.outcome, Outcome::Lost);
+        let solution = game.solution.unwrap();
+        assert_eq!(solution.len(), GROUPS as usize);
+        assert_eq!(solution[0].words, vec!["cake", "pie", "pudding", "cookie"]);
+    }
+
+    #[test]
+    fn test_view_hides_groups() {
+        let (groups, all_words) = setup();
+        let game = GroupThem::new(&all_words, day(), MAXIMUM_BAD_GUESSES)
+            .guess(vec![
+                "cake".to_string(),
+                "pie".to_string(),
+                "pudding".to_string(),
+                "cookie".to_string(),
+            ])
+            .unwrap()
+            .guess(vec![
+                "pen".to_string(),
+                "notebook".to_string(),
+                "stapler".to_string(),
+                "holmes".to_string(),
+            ])
+            .unwrap();
+
+        let view = GroupThemView::from(&game);
+        assert_eq!(view.available_words.len(), 12);
+        assert_eq!(view.solved.len(), 1);
+        assert_eq!(view.solved[0].group, groups[0]);
+        assert!(view.guesses[0].correct);
+        assert_eq!(view.guesses[0].color, Some(game.guesses[0].color.clone()));
+        assert!(!view.guesses[1].correct);
+        assert!(view.guesses[1].color.is_none());
+        assert!(view.solution.is_none());
+
+        // Only the solved group's name is in the response
+        let json = serde_json::to_string(&view).unwrap();
+        assert!(json.contains(&groups[0].name));
+        for group in &groups[1..] {
+            assert!(!json.contains(&group.name));
+        }
+    }
+
+    #[test]
+    fn test_replay_history() {
+        let (_, all_words) = setup();
+        let mut game = GroupThem::new(&all_words, day(), MAXIMUM_BAD_GUESSES);
+
+        // A correct guess, then a wrong one
+        let first: Vec<String> = all_words[..ITEMS_PER_GROUP].iter().map(|w| w.text.clone()).collect();
+        let second: Vec<String> = all_words[ITEMS_PER_GROUP + 1..2 * ITEMS_PER_GROUP + 1].iter().map(|w| w.text.clone()).collect();
+        game = game.guess(first).unwrap().guess(second).unwrap();
+
+        let (initial, guesses) = GameStatus::GroupThem(game.clone()).history();
+        match initial {
+            GameStatus::GroupThem(initial) => {
+                // The words, their order and the colors of every guess are restored as they were
+                let replayed = initial.replay(guesses).unwrap();
+                assert_eq!(replayed.guesses, game.guesses);
+                assert_eq!(replayed.available_words, game.available_words);
+                assert_eq!(replayed.outcome, game.outcome);
+                assert_eq!(serde_json::json!(replayed.view()), serde_json::json!(game.view()));
+            }
+            other => panic!("Expected a GroupThem, got {:?}", other),
+        }
+    }
 }
@@ -0,0 +1,18 @@
+//! The games, their storage and the WordGuess solver behind the `word_games` server.\
+//! Ex. `solver::hint` to compare a model's play against a strong baseline
+pub mod config;
+pub mod db;
+pub mod dictionary;
+pub mod errors;
+pub mod game;
+pub mod groupthem;
+pub mod migrations;
+pub mod models;
+pub mod multiwordguess;
+pub mod openapi;
+pub mod puzzle;
+pub mod registry;
+pub mod session;
+pub mod solver;
+pub mod token;
+pub mod wordguess;
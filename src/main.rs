@@ -1,290 +1,111 @@
-mod game;
-mod groupthem;
-mod wordguess;
-use errors::{DatabaseError, GameError};
-use game::Game;
-use groupthem::{get_data, GroupThem};
-use models::{GameStatus, GroupThemRequest, User, UserRequest, WordGuessRequest};
-mod db;
-mod errors;
-mod models;
-mod token;
-use actix_web::{cookie::Cookie, web, App, HttpResponse, HttpServer, Responder};
-use serde_json::json;
-use wordguess::WordGuess;
-
-async fn get_user(
-    req: actix_web::HttpRequest,
-    conn: &rusqlite::Connection,
-) -> Result<User, DatabaseError> {
-    let stale_token = req
-        .cookie("stale_token")
-        .map(|cookie| cookie.value().to_string());
-    let fresh_token = req
-        .cookie("fresh_token")
-        .map(|cookie| cookie.value().to_string());
-
-    let request = if let (Some(stale_token), Some(fresh_token)) = (stale_token, fresh_token) {
-        UserRequest::Tokens {
-            stale_token,
-            fresh_token,
-        }
-    } else {
-        UserRequest::NewUser
-    };
-
-    db::get_user(conn, request)
-}
-
-async fn groupthem_get_state(
-    req: actix_web::HttpRequest
-) -> impl Responder {
-    
-    let conn = db::initialize_connection(false);
-
-    let user = match get_user(req, &conn).await {
-        Ok(user) => user,
-        Err(e) => {
-            return match e {
-                DatabaseError::FromSQLError(e) => HttpResponse::BadRequest().body(e.to_string()),
-                DatabaseError::GameStatusParseError(e) => {
-                    HttpResponse::InternalServerError().body(format!(
-                        "Your game has been corrupted. Please wait for tomorrow: {}",
-                        e
-                    ))
-                }
-            }
-        }
-    };
-
-    let game = get_data(&conn, 1).await.unwrap();
-
-    let state = match user.game_status.get("group_them") {
-        Some(GameStatus::GroupThem(group_item)) => group_item,
-        _ => &GroupThem::new(&game.1),
-    };
-
-    HttpResponse::Ok().json(state)
+use actix_web::{web, App, HttpResponse, HttpServer};
+use serde_json::Value;
+use word_games::{
+    config::{self, Config},
+    db, dictionary,
+    errors::ApiError,
+    models::TokenResponse,
+    openapi,
+    puzzle::{self, PuzzleProvider},
+    registry, session, token,
+};
+
+/// Mint a session token for a new player.\
+/// Send it back as `Authorization: Bearer <token>`, and replace it with the `X-Session-Token` header of every guess
+async fn create_session(pool: web::Data<db::Pool>) -> Result<HttpResponse, ApiError> {
+    let user = db::run(&pool, db::create_user).await?;
+    let fresh_token = user.fresh_token.unwrap_or_default();
+
+    let mut response = HttpResponse::Ok().json(TokenResponse {
+        token: token::session_token(&user.stale_token, &fresh_token),
+    });
+    session::set_session(&mut response, &user.stale_token, &fresh_token);
+
+    Ok(response)
 }
 
-async fn groupthem_game(
-    payload: web::Json<serde_json::Value>,
-    req: actix_web::HttpRequest
-) -> impl Responder {
-    // Deserialize the request
-    let request = match serde_json::from_value::<GroupThemRequest>(payload.into_inner()) {
-        Ok(data) => data,
-        Err(e) => return HttpResponse::BadRequest().body(e.to_string()),
-    };
-
-    let conn = db::initialize_connection(false);
-
-    let user = match get_user(req, &conn).await {
-        Ok(user) => user,
-        Err(e) => {
-            return match e {
-                DatabaseError::FromSQLError(e) => HttpResponse::BadRequest().body(e.to_string()),
-                DatabaseError::GameStatusParseError(e) => {
-                    HttpResponse::InternalServerError().body(format!(
-                        "Your game has been corrupted. Please wait for tomorrow: {}",
-                        e
-                    ))
-                }
-            }
-        }
-    };
-
-    let game = get_data(&conn, 1).await.unwrap();
-
-
-    let state = match user.game_status.get("group_them") {
-        Some(GameStatus::GroupThem(group_item)) => group_item,
-        _ => &GroupThem::new(&game.1),
-    };
-
-
-    // Make a guess
-    let result = match state.guess(request.guess) {
-        Ok(guess_result) => guess_result,
-        Err(e) => {
-            return match e {
-                GameError::MaximumGuesses => HttpResponse::BadRequest().body(e.to_string()),
-                GameError::GameOver => HttpResponse::Ok().body(e.to_string()),
-                GameError::InvalidGuess(e) => HttpResponse::BadRequest().body(e.to_string()),
-                GameError::NetworkError(e) => HttpResponse::InternalServerError().body(e.to_string())
-            }
-        }
-    };
-
-    // Update the game status with the new result
-    let game_status = GameStatus::GroupThem(result);
-
-    // Update the user in the database
-    let fresh_token = db::update_user_game_status(
-        &conn,
-        &user.stale_token,
-        &game_status
-    ).unwrap();
-
-    let mut response = HttpResponse::Ok().json(game_status);
-
-    let _ = response.add_cookie(
-        &Cookie::build("fresh_token", fresh_token).path("/")
-        .http_only(true)
-        .finish()
-    );
-    let _ = response.add_cookie(
-        &Cookie::build("stale_token", user.stale_token).path("/")
-        .http_only(true)
-        .finish()
-    );
-
-    response
+/// The OpenAPI document of the server
+async fn get_openapi(document: web::Data<Value>) -> HttpResponse {
+    HttpResponse::Ok().json(document.as_ref())
 }
 
-async fn wordguess_game(
-    payload: web::Json<serde_json::Value>,
-    req: actix_web::HttpRequest,
-) -> impl Responder {
-    // Deserialize the request
-    let data: WordGuessRequest =
-        match serde_json::from_value::<WordGuessRequest>(payload.into_inner()) {
-            Ok(data) => data,
-            Err(e) => return HttpResponse::BadRequest().body(e.to_string()),
-        };
-
-    let conn = db::initialize_connection(false);
-
-
-    let user = match get_user(req, &conn).await {
-        Ok(user) => user,
-        Err(e) => {
-            return match e {
-                DatabaseError::FromSQLError(e) => HttpResponse::BadRequest().body(e.to_string()),
-                DatabaseError::GameStatusParseError(e) => {
-                    HttpResponse::InternalServerError().body(format!(
-                        "Your game has been corrupted. Please wait for tomorrow: {}",
-                        e
-                    ))
-                }
-            }
-        }
-    };
-
-    let state = match user.game_status.get("word_guess") {
-        Some(GameStatus::WordGuess(word_guess)) => word_guess,
-        _ => &WordGuess::new(),
-    };
-
-    // Make a guess
-    let result = match state.guess(&data.guess) {
-        Ok(guess_result) => guess_result,
-        Err(e) => {
-            return match e {
-                GameError::MaximumGuesses => HttpResponse::BadRequest().body(e.to_string()),
-                GameError::GameOver => HttpResponse::Ok().body(e.to_string()),
-                GameError::InvalidGuess(e) => HttpResponse::BadRequest().body(e.to_string()),
-                GameError::NetworkError(e) => HttpResponse::InternalServerError().body(e.to_string())
-            }
-        }
-    };
-
-    let game_status = GameStatus::WordGuess(result);
-    
-    // Update the user in the database
-    let fresh_token = db::update_user_game_status(
-        &conn,
-        &user.stale_token,
-        &game_status
-    ).unwrap();
-
-    let mut response = HttpResponse::Ok().json(json!({
-        "game_status": game_status,
-    }));
-
-    let _ = response.add_cookie(
-        &Cookie::build("fresh_token", fresh_token).path("/")
-        .http_only(true)
-        .finish()
-    );
-    let _ = response.add_cookie(
-        &Cookie::build("stale_token", user.stale_token).path("/")
-        .http_only(true)
-        .finish()
-    );
-
-    response
+/// Explore and try the API, from `/openapi.json`
+async fn get_docs() -> HttpResponse {
+    HttpResponse::Ok()
+        .content_type("text/html; charset=utf-8")
+        .body(openapi::EXPLORER)
 }
 
-async fn wordguess_get_state(
-    req: actix_web::HttpRequest
-) -> impl Responder {
-    
-    let conn = db::initialize_connection(false);
-
-    let user = match get_user(req, &conn).await {
-        Ok(user) => user,
-        Err(e) => {
-            return match e {
-                DatabaseError::FromSQLError(e) => HttpResponse::BadRequest().body(e.to_string()),
-                DatabaseError::GameStatusParseError(e) => {
-                    HttpResponse::InternalServerError().body(format!(
-                        "Your game has been corrupted. Please wait for tomorrow: {}",
-                        e
-                    ))
-                }
-            }
-        }
-    };
-
-    let state = match user.game_status.get("word_guess") {
-        Some(GameStatus::WordGuess(word_guess)) => word_guess,
-        _ => &WordGuess::new(),
-    };
-
-    HttpResponse::Ok().json(state)
-}
+#[actix_web::main]
+async fn main() -> std::io::Result<()> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.iter().any(|arg| arg == "--help" || arg == "-h") {
+        print!("{}", config::usage());
+        return Ok(());
+    }
+    let config = Config::load(args, std::env::vars())
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e))?;
 
-async fn get_schema(path: web::Path<String>) -> impl Responder {
-    match path.to_lowercase().as_str() {
-        "wordguess" => HttpResponse::Ok().body(WordGuessRequest::schema()),
-        "groupthem" => HttpResponse::Ok().body(GroupThemRequest::schema()),
-        _ => HttpResponse::NotFound().finish(),
+    if let Some(word_list) = &config.word_list {
+        dictionary::load(word_list, config.answer_list.as_deref())?;
+        println!("Loaded word list from {}", word_list.display());
     }
-}
 
-#[actix_web::main]
-async fn main() -> std::io::Result<()> {
-    println!("Starting server...");
-    HttpServer::new(|| {
-        let json_config = web::JsonConfig::default().limit(200);
+    let provider = puzzle::provider(&config.puzzle_source, config.puzzle_directory.clone())
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e))?;
+    let provider: web::Data<dyn PuzzleProvider> = web::Data::from(std::sync::Arc::from(provider));
+    println!("Serving GroupThem puzzles from `{}`", config.puzzle_source);
+
+    // Open the database once, every worker shares the pool
+    let pool = db::initialize_pool(Some(&config.database_path), config.database_pool_size)
+        .map_err(std::io::Error::other)?;
+    let pool = web::Data::new(pool);
+
+    let bind = (config.bind_address.clone(), config.port);
+    let config = web::Data::new(config);
+
+    let registry = registry::games();
+    let document = web::Data::new(openapi::document(&registry));
+
+    println!("Starting server on {}:{}...", bind.0, bind.1);
+    HttpServer::new(move || {
+        // Bodies that aren't JSON, or are too large, get the same error body as every other error
+        let json_config = web::JsonConfig::default()
+            .limit(config.json_limit)
+            .error_handler(|e, _| ApiError::InvalidRequest(e.to_string()).into());
+        let query_config = web::QueryConfig::default()
+            .error_handler(|e, _| ApiError::InvalidRequest(e.to_string()).into());
         App::new()
+            .app_data(provider.clone())
+            .app_data(pool.clone())
+            .app_data(config.clone())
+            .app_data(document.clone())
+            // GET, POST /{game} and GET /{game}/schema of every registered game
+            .configure(|cfg| registry.configure(cfg))
             .service(
-                web::resource("/wordguess")
-                    .app_data(json_config.clone())
-                    // POST /wordguess
-                    // Make a guess in the word guess game
-                    .route(web::post().to(wordguess_game))
-                    // GET /wordguess
-                    // Get the current state of the word guess game
-                    .route(web::get().to(wordguess_get_state))
+                web::resource("/token")
+                    // POST /token
+                    // Create a player and get their session token
+                    .route(web::post().to(create_session))
             )
             .service(
-                web::resource("/{segment}/schema")
-                    // GET /{segment}/schema
-                    // Get the schema used for POST requests
-                    // Ex. GET /wordguess/schema
-                    .route(web::get().to(get_schema)),
+                web::resource("/openapi.json")
+                    // GET /openapi.json
+                    // Get the OpenAPI document of every route
+                    .route(web::get().to(get_openapi))
             )
             .service(
-                web::resource("/groupthem")
-                    .app_data(json_config.clone())
-                    .route(web::post().to(groupthem_game))
-                    .route(web::get().to(groupthem_get_state))
+                web::resource("/docs")
+                    // GET /docs
+                    // Explore and try the API
+                    .route(web::get().to(get_docs))
             )
+            // Every other route, ex. a game that isn't registered
+            .default_service(web::to(registry::unknown_game))
             .app_data(json_config)
+            .app_data(query_config)
     })
-    .bind(("127.0.0.1", 8080))?
+    .bind(bind)?
     .shutdown_timeout(10)
     .run()
     .await
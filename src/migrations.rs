@@ -0,0 +1,388 @@
+use rusqlite::{params, Connection, Transaction, TransactionBehavior};
+
+use std::collections::HashMap;
+
+use crate::{errors::DatabaseError, models::GameStatus};
+
+/// A change to the database schema.\
+/// Migrations are applied in `version` order, and each one is only ever applied once
+pub struct Migration {
+    pub version: u32,
+    pub description: &'static str,
+    pub step: Step,
+}
+
+/// The work done by a migration
+pub enum Step {
+    /// SQL statements, run as a batch
+    Sql(&'static str),
+    /// Rust code, for data that can't be moved with SQL alone
+    Function(fn(&Transaction) -> Result<(), DatabaseError>),
+}
+
+/// Every migration, in order.\
+/// Never edit or reorder a migration that was released, add a new one instead
+pub static MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        description: "Create the users and game_cache tables",
+        // `IF NOT EXISTS` adopts databases created before migrations existed
+        step: Step::Sql(
+            "CREATE TABLE IF NOT EXISTS users (
+                stale_token TEXT PRIMARY KEY,
+                fresh_token TEXT NOT NULL,
+                game_status TEXT
+            );
+            CREATE TABLE IF NOT EXISTS game_cache (
+                day_id TEXT PRIMARY KEY,
+                game_cache TEXT NOT NULL
+            );",
+        ),
+    },
+    Migration {
+        version: 2,
+        description: "Create the games and guesses tables",
+        step: Step::Sql(
+            "CREATE TABLE games (
+                stale_token TEXT NOT NULL,
+                game TEXT NOT NULL,
+                puzzle_date TEXT NOT NULL,
+                initial_state TEXT NOT NULL,
+                PRIMARY KEY (stale_token, game, puzzle_date)
+            );
+            CREATE TABLE guesses (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                stale_token TEXT NOT NULL,
+                game TEXT NOT NULL,
+                puzzle_date TEXT NOT NULL,
+                guess TEXT NOT NULL,
+                result TEXT NOT NULL,
+                created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+                FOREIGN KEY (stale_token, game, puzzle_date) REFERENCES games (stale_token, game, puzzle_date)
+            );
+            CREATE INDEX guesses_by_game ON guesses (stale_token, game, puzzle_date, id);",
+        ),
+    },
+    Migration {
+        version: 3,
+        description: "Move the game_status of every user into the guesses table",
+        step: Step::Function(move_game_status_to_guesses),
+    },
+    Migration {
+        version: 4,
+        description: "Keep the previous fresh token of every user",
+        step: Step::Sql(
+            "ALTER TABLE users ADD COLUMN previous_fresh_token TEXT;
+            ALTER TABLE users ADD COLUMN rotated_at TEXT;",
+        ),
+    },
+];
+
+/// Split every stored `users.game_status` map into a game and its guesses.\
+/// The guesses get the time of the migration, their real time was never stored.\
+/// A map that can't be read is left in place and reported, so one corrupted player doesn't block the server
+fn move_game_status_to_guesses(transaction: &Transaction) -> Result<(), DatabaseError> {
+    let mut stmt = transaction
+        .prepare("SELECT stale_token, game_status FROM users WHERE game_status IS NOT NULL")?;
+    let users = stmt
+        .query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))?
+        .collect::<Result<Vec<(String, String)>, rusqlite::Error>>()?;
+
+    for (stale_token, game_status) in users {
+        let game_status: HashMap<String, GameStatus> = match serde_json::from_str(&game_status) {
+            Ok(game_status) => game_status,
+            Err(e) => {
+                eprintln!("Unable to migrate the games of {}: {}", stale_token, e);
+                continue;
+            }
+        };
+
+        for status in game_status.values() {
+            let game = status.to_string();
+            let day = status.day().format("%Y-%m-%d").to_string();
+            let (initial_state, guesses) = status.history();
+
+            transaction.execute(
+                "INSERT INTO games (stale_token, game, puzzle_date, initial_state) VALUES (?1, ?2, ?3, ?4)",
+                params![
+                    stale_token,
+                    game,
+                    day,
+                    serde_json::to_string(&initial_state)
+                        .map_err(|e| DatabaseError::GameStatusParseError(e.to_string()))?
+                ],
+            )?;
+            for (guess, result) in guesses {
+                transaction.execute(
+                    "INSERT INTO guesses (stale_token, game, puzzle_date, guess, result) VALUES (?1, ?2, ?3, ?4, ?5)",
+                    params![stale_token, game, day, guess.to_string(), result.to_string()],
+                )?;
+            }
+        }
+
+        transaction.execute(
+            "UPDATE users SET game_status = NULL WHERE stale_token = ?1",
+            [&stale_token],
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Bring the database up to the latest schema
+/// ### Arguments
+/// * `conn` - A connection to the database
+/// ### Returns
+/// The schema version of the database
+/// ### Errors
+/// Returns a `DatabaseError::MigrationError` if a migration fails, or the database was created by a newer version
+pub fn migrate(conn: &Connection) -> Result<u32, DatabaseError> {
+    apply(conn, MIGRATIONS)
+}
+
+/// The schema version of the database, `0` when no migration was applied
+pub fn schema_version(conn: &Connection) -> Result<u32, DatabaseError> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS schema_version (
+            version INTEGER PRIMARY KEY,
+            description TEXT NOT NULL,
+            applied_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+        )",
+        [],
+    )?;
+
+    Ok(conn.query_row("SELECT COALESCE(MAX(version), 0) FROM schema_version", [], |row| {
+        row.get(0)
+    })?)
+}
+
+/// Apply every migration newer than the database.\
+/// Each migration runs in its own transaction, so a failed migration leaves the database at the previous version
+fn apply(conn: &Connection, migrations: &[Migration]) -> Result<u32, DatabaseError> {
+    let current = schema_version(conn)?;
+    let latest = migrations.last().map_or(0, |m| m.version);
+    if current > latest {
+        return Err(DatabaseError::MigrationError(format!(
+            "the database is at version {} but this server only knows version {}",
+            current, latest
+        )));
+    }
+
+    for migration in migrations.iter().filter(|m| m.version > current) {
+        let transaction = Transaction::new_unchecked(conn, TransactionBehavior::Immediate)?;
+
+        // Another server may have applied it while waiting for the lock
+        if schema_version(&transaction)? >= migration.version {
+            continue;
+        }
+
+        match &migration.step {
+            Step::Sql(sql) => transaction.execute_batch(sql).map_err(DatabaseError::from),
+            Step::Function(function) => function(&transaction),
+        }
+        .map_err(|e| {
+            DatabaseError::MigrationError(format!(
+                "migration {} ({}) failed: {}",
+                migration.version, migration.description, e
+            ))
+        })?;
+
+        transaction.execute(
+            "INSERT INTO schema_version (version, description) VALUES (?1, ?2)",
+            params![migration.version, migration.description],
+        )?;
+        transaction.commit()?;
+    }
+
+    schema_version(conn)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn table_exists(conn: &Connection, table: &str) -> bool {
+        conn.query_row(
+            "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name = ?1",
+            [table],
+            |row| row.get::<_, u32>(0),
+        )
+        .unwrap()
+            == 1
+    }
+
+    #[test]
+    fn test_migrations_are_ordered() {
+        for (i, migration) in MIGRATIONS.iter().enumerate() {
+            assert_eq!(migration.version, i as u32 + 1, "{}", migration.description);
+        }
+    }
+
+    #[test]
+    fn test_migrate_new_database() {
+        let conn = Connection::open_in_memory().unwrap();
+        let latest = MIGRATIONS.last().unwrap().version;
+
+        assert_eq!(migrate(&conn).unwrap(), latest);
+        assert!(table_exists(&conn, "users"));
+        assert!(table_exists(&conn, "game_cache"));
+
+        // Running again is a no-op
+        assert_eq!(migrate(&conn).unwrap(), latest);
+        let applied: u32 = conn
+            .query_row("SELECT COUNT(*) FROM schema_version", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(applied, latest);
+    }
+
+    #[test]
+    fn test_migrate_keeps_existing_players() {
+        // A database created before migrations existed
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE users (
+                stale_token TEXT PRIMARY KEY,
+                fresh_token TEXT NOT NULL,
+                game_status TEXT
+            );
+            INSERT INTO users VALUES ('stale', 'fresh', '{}');",
+        )
+        .unwrap();
+
+        migrate(&conn).unwrap();
+
+        let fresh_token: String = conn
+            .query_row("SELECT fresh_token FROM users WHERE stale_token = 'stale'", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(fresh_token, "fresh");
+    }
+
+    #[test]
+    fn test_failed_migration_is_rolled_back() {
+        fn fail(_: &Transaction) -> Result<(), DatabaseError> {
+            Err(DatabaseError::MigrationError("on purpose".to_string()))
+        }
+
+        let migrations = [
+            Migration {
+                version: 1,
+                description: "Create a table",
+                step: Step::Sql("CREATE TABLE first (id INTEGER PRIMARY KEY);"),
+            },
+            Migration {
+                version: 2,
+                description: "Create a table, then fail",
+                step: Step::Sql("CREATE TABLE second (id INTEGER PRIMARY KEY); SELECT * FROM missing;"),
+            },
+            Migration {
+                version: 3,
+                description: "Fail from Rust",
+                step: Step::Function(fail),
+            },
+        ];
+
+        let conn = Connection::open_in_memory().unwrap();
+        assert!(matches!(
+            apply(&conn, &migrations),
+            Err(DatabaseError::MigrationError(_))
+        ));
+        assert_eq!(schema_version(&conn).unwrap(), 1);
+        assert!(table_exists(&conn, "first"));
+        assert!(!table_exists(&conn, "second"));
+
+        assert!(matches!(
+            apply(&conn, &migrations[2..]),
+            Err(DatabaseError::MigrationError(_))
+        ));
+        assert_eq!(schema_version(&conn).unwrap(), 1);
+    }
+
+    #[test]
+    fn test_rust_migration() {
+        fn seed(transaction: &Transaction) -> Result<(), DatabaseError> {
+            for id in 0..3 {
+                transaction.execute("INSERT INTO items (id) VALUES (?1)", [id])?;
+            }
+            Ok(())
+        }
+
+        let migrations = [
+            Migration {
+                version: 1,
+                description: "Create items",
+                step: Step::Sql("CREATE TABLE items (id INTEGER PRIMARY KEY);"),
+            },
+            Migration {
+                version: 2,
+                description: "Seed items",
+                step: Step::Function(seed),
+            },
+        ];
+
+        let conn = Connection::open_in_memory().unwrap();
+        assert_eq!(apply(&conn, &migrations).unwrap(), 2);
+        let items: u32 = conn
+            .query_row("SELECT COUNT(*) FROM items", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(items, 3);
+    }
+
+    #[test]
+    fn test_reject_newer_database() {
+        let conn = Connection::open_in_memory().unwrap();
+        migrate(&conn).unwrap();
+        conn.execute(
+            "INSERT INTO schema_version (version, description) VALUES (999, 'From the future')",
+            [],
+        )
+        .unwrap();
+
+        assert!(matches!(migrate(&conn), Err(DatabaseError::MigrationError(_))));
+    }
+
+    #[test]
+    fn test_move_game_status_to_guesses() {
+        use chrono::NaiveDate;
+        use serde_json::json;
+        use crate::{db, game::Game, wordguess::{WordGuess, MAXIMUM_GUESSES}};
+
+        let day = NaiveDate::from_ymd_opt(2025, 1, 1).unwrap();
+        let played = WordGuess::new(day, MAXIMUM_GUESSES)
+            .guess("crane")
+            .unwrap()
+            .guess("slate")
+            .unwrap();
+        let game_status = HashMap::from([(
+            "word_guess:2025-01-01".to_string(),
+            GameStatus::WordGuess(played.clone()),
+        )]);
+
+        // A player saved before the guesses table existed
+        let conn = Connection::open_in_memory().unwrap();
+        apply(&conn, &MIGRATIONS[..2]).unwrap();
+        conn.execute_batch("INSERT INTO users VALUES ('corrupted', 'fresh', 'not json')").unwrap();
+        conn.execute(
+            "INSERT INTO users VALUES ('stale', 'fresh', ?1)",
+            [serde_json::to_string(&game_status).unwrap()],
+        )
+        .unwrap();
+
+        migrate(&conn).unwrap();
+
+        match db::get_game::<WordGuess>(&conn, "stale", day).unwrap() {
+            Some((word_guess, _)) => assert_eq!(json!(word_guess), json!(played)),
+            other => panic!("Expected a WordGuess, got {:?}", other),
+        }
+        assert_eq!(db::get_guess_history(&conn, "stale").unwrap().len(), 2);
+
+        // The moved map is cleared, the unreadable one is kept
+        let remaining: Vec<String> = conn
+            .prepare("SELECT stale_token FROM users WHERE game_status IS NOT NULL")
+            .unwrap()
+            .query_map([], |row| row.get(0))
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(remaining, ["corrupted"]);
+    }
+}
@@ -1,11 +1,12 @@
 use core::fmt;
-use std::{collections::HashMap, hash::Hash};
+use std::hash::Hash;
 
+use chrono::NaiveDate;
 use palette::{IntoColor, Lch, Mix, Srgb};
 use rand::Rng;
 use serde::{Deserialize, Serialize};
-use schemars::{schema_for, JsonSchema};
-use crate::{errors::GameError, groupthem::GroupThem, wordguess::WordGuess};
+use schemars::JsonSchema;
+use crate::{errors::GameError, groupthem::GroupThem, multiwordguess::MultiWordGuess, wordguess::WordGuess};
 
 /*
     HTTP Request Models
@@ -15,40 +16,106 @@ pub struct GroupThemRequest {
     pub guess: Vec<String>,
 }
 
-impl GroupThemRequest {
-    pub fn schema() -> String {
-        serde_json::to_string_pretty(&schema_for!(GroupThemRequest)).unwrap()
-    }
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct WordGuessRequest {
+    pub guess: String,
+}
+
+/// The variant of a new WordGuess game, ex. `GET /wordguess?hard_mode=true`
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema)]
+pub struct WordGuessOptions {
+    /// Every revealed hint has to be used in later guesses
+    pub hard_mode: Option<bool>,
+    /// There is no answer, every guess gets the feedback that keeps the most answers possible, as in Absurdle
+    pub adversarial: Option<bool>,
+    /// The length of the answer, from 4 to 11 letters (5)
+    pub letters: Option<usize>,
+    /// The number of guesses before the game is lost, from 1 to 20 (the server's `maximum_guesses`)
+    pub maximum_guesses: Option<usize>,
+}
+
+/// The query of `GET /wordguess/hint`
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema)]
+pub struct HintQuery {
+    /// The number of candidates and suggested guesses, from 1 to 100 (5)
+    pub limit: Option<usize>,
 }
 
 #[derive(Debug, Serialize, Deserialize, JsonSchema)]
-pub struct WordGuessRequest {
+pub struct MultiWordGuessRequest {
+    /// The word played on every unsolved board
     pub guess: String,
 }
 
-impl WordGuessRequest {
-    pub fn schema() -> String {
-        serde_json::to_string_pretty(&schema_for!(WordGuessRequest)).unwrap()
-    }
+/// The variant of a new MultiWordGuess game, ex. `GET /multiwordguess?boards=8`
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema)]
+pub struct MultiWordGuessOptions {
+    /// The number of boards, from 2 to 8 (4)
+    pub boards: Option<usize>,
+    /// The length of the answers, from 4 to 11 letters (5)
+    pub letters: Option<usize>,
+    /// The number of guesses shared by every board, from 1 to 20 (5 more than the boards)
+    pub maximum_guesses: Option<usize>,
 }
 
 /*
     END HTTP Request Models
 */
 
+/*
+    HTTP Response Models
+*/
+
+/// The body of `POST /token`
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct TokenResponse {
+    /// The session token, `<stale_token>.<fresh_token>`. Send it as `Authorization: Bearer <token>`
+    pub token: String,
+}
+
+/// The body answering a WordGuess guess
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct WordGuessResponse {
+    pub game_status: WordGuess,
+}
+
+/// The body answering a MultiWordGuess guess
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct MultiWordGuessResponse {
+    pub game_status: MultiWordGuess,
+}
+
+/*
+    END HTTP Response Models
+*/
+
+#[allow(dead_code)]
 #[derive(Debug, Serialize, Deserialize)]
 pub enum GuessInput {
     Single(String),
     Multiple(Vec<String>),
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct User {
     pub stale_token: String,
     pub fresh_token: Option<String>,
-    pub game_status: HashMap<String, GameStatus>,
 }
 
+/// A guess stored in the `guesses` table\
+/// `guess`: The guess as sent by the player\
+/// `result`: The feedback the guess got
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct GuessRecord {
+    pub id: i64,
+    pub game: String,
+    pub puzzle_date: NaiveDate,
+    pub guess: serde_json::Value,
+    pub result: serde_json::Value,
+    pub created_at: String,
+}
+
+#[allow(dead_code)]
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Token {
     pub stale: String,
@@ -71,7 +138,7 @@ pub enum UserRequest {
  * It can either be a `Good` or `Bad` guess
 */
 
-#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Hash, Clone)]
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Hash, Clone, PartialOrd, Ord, JsonSchema)]
 pub enum Ranking {
     Easy,
     Medium,
@@ -111,7 +178,7 @@ impl Ranking {
     }
 }
 
-#[derive(Debug, Deserialize, Serialize, PartialEq, Eq, Hash, Clone)]
+#[derive(Debug, Deserialize, Serialize, PartialEq, Eq, Hash, Clone, JsonSchema)]
 pub struct ApproxColor {
     pub red: u8,
     pub green: u8,
@@ -159,7 +226,7 @@ pub fn mix_colors(words: &[Word]) -> ApproxColor {
     color.into()
 }
 
-#[derive(Debug, Serialize, Deserialize, PartialEq, Hash, Eq, Clone)]
+#[derive(Debug, Serialize, Deserialize, PartialEq, Hash, Eq, Clone, JsonSchema)]
 pub struct Group {
     pub name: String,
     pub ranking: Ranking,
@@ -172,7 +239,7 @@ impl Group {
 }
 
 
-#[derive(Debug, PartialEq, Hash, Eq, Clone, Serialize, Deserialize)]
+#[derive(Debug, PartialEq, Hash, Eq, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct Word {
     pub text: String,
     pub group: Group,
@@ -189,21 +256,52 @@ impl Word {
                 group: word.group.clone(),
             })
         } else {
-            Err(GameError::InvalidGuess(format!(
-                "`{}` is not a valid word",
-                text
-            )))
+            Err(GameError::NotOnBoard(text.to_string()))
         }
     }
 }
 
-#[derive(Debug, Serialize, Deserialize, Hash, PartialEq, Clone, Eq)]
+/// Feedback on a GroupThem guess\
+/// `most_common`: How many of the guessed words share the most common group\
+/// `one_away`: Whether the guess was one word away from a group
+#[derive(Debug, Serialize, Deserialize, Hash, PartialEq, Clone, Eq, Default, JsonSchema)]
+pub struct GroupFeedback {
+    pub most_common: usize,
+    pub one_away: bool,
+}
+
+impl GroupFeedback {
+    pub fn from_words(words: &[Word]) -> Self {
+        let most_common = words
+            .iter()
+            .map(|word| words.iter().filter(|w| w.group == word.group).count())
+            .max()
+            .unwrap_or(0);
+
+        GroupFeedback {
+            most_common,
+            one_away: most_common + 1 == words.len(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Hash, PartialEq, Clone, Eq, JsonSchema)]
 pub struct GroupResult {
     pub words: Vec<Word>,
     pub color: ApproxColor,
+    #[serde(default)]
+    pub feedback: GroupFeedback,
 }
 
 impl GroupResult {
+    pub fn new(words: Vec<Word>) -> Self {
+        GroupResult {
+            color: mix_colors(&words),
+            feedback: GroupFeedback::from_words(&words),
+            words,
+        }
+    }
+
     pub fn is_group(&self) -> bool {
         self.words
             .iter()
@@ -211,6 +309,14 @@ impl GroupResult {
     }
 }
 
+/// A group with all of its words, revealed once a GroupThem game is over
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, JsonSchema)]
+pub struct SolvedGroup {
+    pub group: Group,
+    pub words: Vec<String>,
+}
+
+#[allow(dead_code)]
 #[derive(Debug, Hash, PartialEq, Clone, Eq)]
 pub struct GroupData {
     pub words: Vec<Word>,
@@ -220,15 +326,146 @@ pub struct GroupData {
     End of GroupGuess Models
 */
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
-#[serde(untagged)]
+/// The version of the stored `GameStatus` format
+pub static GAME_STATUS_VERSION: u64 = 1;
+
+/// The state of a user's game.\
+/// Serialized with the game it belongs to and the version of the format
+/// ```json
+/// {"version": 1, "game": "word_guess", "state": {"guesses": [], ...}}
+/// ```
+/// States stored before the format was versioned are read by `GameStatus::from_legacy`.\
+/// Only the games that were stored in `users.game_status` have a variant, it is used to migrate them.
+/// Registered games are read and written as a `StoredState`
+#[derive(Debug, Clone)]
 pub enum GameStatus {
-    #[serde(rename = "word_guess")]
     WordGuess(WordGuess),
-    #[serde(rename = "group_them")]
     GroupThem(GroupThem),
 }
 
+/// A game's state as stored in the `games` table, in the same format as a `GameStatus`
+#[derive(Serialize, Deserialize)]
+pub struct StoredState<T> {
+    pub version: u64,
+    pub game: String,
+    pub state: T,
+}
+
+/// The serialized form of a `GameStatus`
+#[derive(Serialize)]
+struct VersionedGameStatus<'a> {
+    version: u64,
+    #[serde(flatten)]
+    status: TaggedGameStatus<'a>,
+}
+
+#[derive(Serialize)]
+#[serde(tag = "game", content = "state", rename_all = "snake_case")]
+enum TaggedGameStatus<'a> {
+    WordGuess(&'a WordGuess),
+    GroupThem(&'a GroupThem),
+}
+
+/// The deserialized form of a `GameStatus`, version 1
+#[derive(Deserialize)]
+#[serde(tag = "game", content = "state", rename_all = "snake_case")]
+enum GameStatusV1 {
+    WordGuess(WordGuess),
+    GroupThem(GroupThem),
+}
+
+impl Serialize for GameStatus {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let status = match self {
+            GameStatus::WordGuess(word_guess) => TaggedGameStatus::WordGuess(word_guess),
+            GameStatus::GroupThem(group_them) => TaggedGameStatus::GroupThem(group_them),
+        };
+
+        VersionedGameStatus {
+            version: GAME_STATUS_VERSION,
+            status,
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for GameStatus {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = serde_json::Value::deserialize(deserializer)?;
+
+        let status = match value.get("version") {
+            None => GameStatus::from_legacy(value),
+            Some(version) if version.as_u64() == Some(1) => {
+                let mut value = value;
+                if let Some(fields) = value.as_object_mut() {
+                    fields.remove("version");
+                }
+                serde_json::from_value(value)
+                    .map(|status| match status {
+                        GameStatusV1::WordGuess(word_guess) => GameStatus::WordGuess(word_guess),
+                        GameStatusV1::GroupThem(group_them) => GameStatus::GroupThem(group_them),
+                    })
+                    .map_err(|e| e.to_string())
+            }
+            Some(version) => Err(format!("Unsupported game status version {}", version)),
+        };
+
+        status.map_err(serde::de::Error::custom)
+    }
+}
+
+impl GameStatus {
+    /// Read a state stored before `GameStatus` was tagged, which was the game's state alone.\
+    /// The game is told apart by a field only it has, instead of trying each game in turn
+    fn from_legacy(value: serde_json::Value) -> Result<Self, String> {
+        if value.get("available_words").is_some() {
+            serde_json::from_value(value)
+                .map(GameStatus::GroupThem)
+                .map_err(|e| format!("Invalid group_them state: {}", e))
+        } else if value.get("maximum_guesses").is_some() {
+            serde_json::from_value(value)
+                .map(GameStatus::WordGuess)
+                .map_err(|e| format!("Invalid word_guess state: {}", e))
+        } else {
+            Err("Unknown game state".to_string())
+        }
+    }
+
+    /// The day of the puzzle this state belongs to
+    pub fn day(&self) -> NaiveDate {
+        match self {
+            GameStatus::WordGuess(word_guess) => word_guess.day,
+            GameStatus::GroupThem(group_them) => group_them.day,
+        }
+    }
+
+    /// Split a game into the state it started from and its guesses with their feedback.\
+    /// Used to move games stored as a single state into the `guesses` table
+    pub fn history(&self) -> (GameStatus, Vec<(serde_json::Value, serde_json::Value)>) {
+        match self {
+            GameStatus::WordGuess(word_guess) => (
+                GameStatus::WordGuess(word_guess.restart()),
+                word_guess
+                    .guesses
+                    .iter()
+                    .map(|result| (serde_json::json!(result.to_string()), serde_json::json!(result)))
+                    .collect(),
+            ),
+            GameStatus::GroupThem(group_them) => (
+                GameStatus::GroupThem(group_them.restart()),
+                group_them
+                    .guesses
+                    .iter()
+                    .map(|result| {
+                        let words: Vec<&String> = result.words.iter().map(|w| &w.text).collect();
+                        (serde_json::json!(words), serde_json::json!(result))
+                    })
+                    .collect(),
+            ),
+        }
+    }
+}
+
 impl fmt::Display for GameStatus {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
@@ -236,4 +473,83 @@ impl fmt::Display for GameStatus {
             GameStatus::GroupThem(_) => write!(f, "group_them"),
         }
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{game::Game, wordguess::MAXIMUM_GUESSES};
+
+    fn day() -> NaiveDate {
+        NaiveDate::from_ymd_opt(2025, 1, 1).unwrap()
+    }
+
+    fn word_guess() -> WordGuess {
+        WordGuess::new(day(), MAXIMUM_GUESSES).guess("crane").unwrap()
+    }
+
+    fn group_them() -> GroupThem {
+        let words: Vec<Word> = (0..16)
+            .map(|i| Word {
+                text: format!("w{}", i),
+                group: Group {
+                    name: format!("group {}", i / 4),
+                    ranking: Ranking::from_index(i / 4),
+                },
+            })
+            .collect();
+        GroupThem::new(&words, day(), 4)
+            .guess(vec!["w0".to_string(), "w1".to_string(), "w2".to_string(), "w4".to_string()])
+            .unwrap()
+    }
+
+    #[test]
+    fn test_tagged_round_trip() {
+        for status in [GameStatus::WordGuess(word_guess()), GameStatus::GroupThem(group_them())] {
+            let json = serde_json::to_value(&status).unwrap();
+            assert_eq!(json["version"], GAME_STATUS_VERSION);
+            assert_eq!(json["game"], status.to_string());
+
+            let decoded: GameStatus = serde_json::from_value(json.clone()).unwrap();
+            assert_eq!(decoded.to_string(), status.to_string());
+            assert_eq!(serde_json::to_value(&decoded).unwrap(), json);
+        }
+    }
+
+    #[test]
+    fn test_read_legacy_states() {
+        // Before the format was tagged, a stored state was the game's state alone
+        let word_guess = serde_json::to_value(word_guess()).unwrap();
+        let group_them = serde_json::to_value(group_them()).unwrap();
+        let legacy = serde_json::json!({
+            "word_guess:2025-01-01": word_guess,
+            "group_them:2025-01-01": group_them,
+        });
+
+        let decoded: std::collections::HashMap<String, GameStatus> =
+            serde_json::from_value(legacy).unwrap();
+        match &decoded["word_guess:2025-01-01"] {
+            GameStatus::WordGuess(state) => assert_eq!(serde_json::to_value(state).unwrap(), word_guess),
+            other => panic!("Expected a WordGuess, got {:?}", other),
+        }
+        match &decoded["group_them:2025-01-01"] {
+            GameStatus::GroupThem(state) => assert_eq!(serde_json::to_value(state).unwrap(), group_them),
+            other => panic!("Expected a GroupThem, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_reject_unknown_states() {
+        let mut json = serde_json::to_value(GameStatus::WordGuess(word_guess())).unwrap();
+
+        // A WordGuess state tagged as another game isn't decoded as that game
+        json["game"] = "group_them".into();
+        assert!(serde_json::from_value::<GameStatus>(json.clone()).is_err());
+
+        json["game"] = "word_guess".into();
+        json["version"] = 99.into();
+        let error = serde_json::from_value::<GameStatus>(json).unwrap_err();
+        assert!(error.to_string().contains("Unsupported game status version 99"));
+
+        assert!(serde_json::from_str::<GameStatus>(r#"{"guesses": []}"#).is_err());
+    }
+}
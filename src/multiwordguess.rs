@@ -0,0 +1,343 @@
+use std::ops::RangeInclusive;
+
+use chrono::NaiveDate;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    dictionary::LETTERS,
+    errors::GameError,
+    game::{Game, Outcome},
+    models::{MultiWordGuessOptions, MultiWordGuessRequest, MultiWordGuessResponse},
+    registry::{GameContext, GameFuture, GameMetadata},
+    wordguess::{answers_for, WordGuess, WordResult},
+};
+
+/// The number of boards of a game started without options, as in Quordle
+pub static BOARDS: usize = 4;
+/// The board counts a game can be started with, from Dordle to Octordle
+pub static BOARD_COUNTS: RangeInclusive<usize> = 2..=8;
+/// The guesses a game has on top of one per board, ex. 9 guesses for 4 boards
+pub static EXTRA_GUESSES: usize = 5;
+
+/// Several `WordGuess` boards played at once, each with its own answer.\
+/// Every guess is scored against each unsolved board, and the boards share one guess budget
+#[derive(Debug, Deserialize, Serialize, Clone, JsonSchema)]
+pub struct MultiWordGuess {
+    /// Every guess made, oldest first
+    pub guesses: Vec<String>,
+    /// The boards, with the feedback of every guess made until they were solved
+    pub boards: Vec<WordGuess>,
+    pub maximum_guesses: usize,
+    /// The length of the answers
+    pub letters: usize,
+    pub day: NaiveDate,
+    #[serde(default)]
+    pub outcome: Outcome,
+}
+
+impl MultiWordGuess {
+    /// Start the puzzle for `day`
+    /// ### Arguments
+    /// * `day` - The day of the puzzle, which picks the answers
+    /// * `boards` - The number of boards, in `BOARD_COUNTS`
+    /// * `letters` - The length of the answers, in `WORD_LENGTHS`
+    /// * `maximum_guesses` - The number of guesses shared by every board, in `GUESS_LIMITS`
+    /// ### Errors
+    /// Returns a `GameError::InvalidVariant` when an argument is out of its range
+    pub fn new(day: NaiveDate, boards: usize, letters: usize, maximum_guesses: usize) -> Result<Self, GameError> {
+        if !BOARD_COUNTS.contains(&boards) {
+            return Err(GameError::InvalidVariant(format!(
+                "A game has {} to {} boards",
+                BOARD_COUNTS.start(),
+                BOARD_COUNTS.end()
+            )));
+        }
+
+        let board = WordGuess::new(day, maximum_guesses)
+            .with_letters(letters)?
+            .with_maximum_guesses(maximum_guesses)?;
+        let answers = answers_for(day, letters, boards).ok_or_else(|| {
+            GameError::InvalidVariant(format!("There aren't {} answers of {} letters", boards, letters))
+        })?;
+
+        Ok(MultiWordGuess {
+            guesses: Vec::new(),
+            boards: answers
+                .into_iter()
+                .map(|answer| {
+                    let mut board = board.clone();
+                    board.answer = answer;
+                    board
+                })
+                .collect(),
+            maximum_guesses,
+            letters,
+            day,
+            outcome: Outcome::InProgress,
+        })
+    }
+
+    /// Determine the outcome from the boards.\
+    /// The game is won once every board is solved, and lost when the guesses run out before that
+    fn evaluate(&self) -> Outcome {
+        if self.boards.iter().all(|board| board.outcome == Outcome::Won) {
+            Outcome::Won
+        } else if self.guesses.len() >= self.maximum_guesses {
+            Outcome::Lost
+        } else {
+            Outcome::InProgress
+        }
+    }
+
+    /// Restore the answers of a stored game.\
+    /// The answers are never serialized, so they have to be recomputed from `day`, `letters` and the number of boards
+    pub fn resume(mut self) -> Self {
+        let answers = answers_for(self.day, self.letters, self.boards.len()).unwrap_or_default();
+        for (board, answer) in self.boards.iter_mut().zip(answers) {
+            board.answer = answer;
+        }
+        self
+    }
+}
+
+impl<T: AsRef<str>> Game<T, String> for MultiWordGuess {
+    type State = Self;
+    type GameError = GameError;
+    /// The feedback of every board, `None` for the boards solved before the guess
+    type GameResult = Vec<Option<WordResult>>;
+
+    fn guess(&self, guess: T) -> Result<Self, GameError> {
+        let guess = self.clean(guess)?;
+
+        let boards = self
+            .boards
+            .iter()
+            .map(|board| match board.outcome {
+                Outcome::InProgress => board.guess(&guess),
+                _ => Ok(board.clone()),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut state = MultiWordGuess {
+            guesses: {
+                let mut new_guesses = self.guesses.clone();
+                new_guesses.push(guess);
+                new_guesses
+            },
+            boards,
+            ..self.clone()
+        };
+        state.outcome = state.evaluate();
+        Ok(state)
+    }
+
+    fn process(&self, guess: String) -> Result<Vec<Option<WordResult>>, GameError> {
+        self.boards
+            .iter()
+            .map(|board| match board.outcome {
+                Outcome::InProgress => Game::<&str, String>::process(board, guess.clone()).map(Some),
+                _ => Ok(None),
+            })
+            .collect()
+    }
+
+    fn clean(&self, guess: T) -> Result<String, GameError> {
+        // * The game is already over
+        match self.evaluate() {
+            Outcome::Won => return Err(GameError::GameOver),
+            Outcome::Lost => return Err(GameError::MaximumGuesses),
+            Outcome::InProgress => {}
+        }
+
+        // * Every unsolved board has seen every guess, so any of them checks the guess
+        self.boards
+            .iter()
+            .find(|board| !board.outcome.is_over())
+            .ok_or(GameError::GameOver)?
+            .clean(guess)
+    }
+}
+
+impl GameMetadata for MultiWordGuess {
+    const ROUTE: &'static str = "multiwordguess";
+    const KEY: &'static str = "multi_word_guess";
+    const NAME: &'static str = "MultiWordGuess";
+    const DESCRIPTION: &'static str =
+        "Guess the words of several boards at once, as in Dordle or Quordle. Every guess is played on each unsolved board, and the boards share the guesses";
+
+    type Options = MultiWordGuessOptions;
+    type Request = MultiWordGuessRequest;
+    type Guess = String;
+    type Clean = String;
+    type Feedback = Vec<Option<WordResult>>;
+    type View = MultiWordGuess;
+    type Response = MultiWordGuessResponse;
+
+    fn start<'a>(_context: &'a GameContext<'a>, day: NaiveDate, options: &'a MultiWordGuessOptions) -> GameFuture<'a, Self> {
+        let boards = options.boards.unwrap_or(BOARDS);
+        let game = MultiWordGuess::new(
+            day,
+            boards,
+            options.letters.unwrap_or(LETTERS),
+            options.maximum_guesses.unwrap_or(boards + EXTRA_GUESSES),
+        );
+        Box::pin(async move { game })
+    }
+
+    fn check_options(&self, options: &MultiWordGuessOptions) -> Result<(), GameError> {
+        if options.boards.is_some_and(|boards| boards != self.boards.len()) {
+            return Err(GameError::VariantMismatch(format!(
+                "Today's game has {} boards",
+                self.boards.len()
+            )));
+        }
+        if options.letters.is_some_and(|letters| letters != self.letters) {
+            return Err(GameError::VariantMismatch(format!(
+                "Today's game has {} letters",
+                self.letters
+            )));
+        }
+        if options.maximum_guesses.is_some_and(|maximum_guesses| maximum_guesses != self.maximum_guesses) {
+            return Err(GameError::VariantMismatch(format!(
+                "Today's game has {} guesses",
+                self.maximum_guesses
+            )));
+        }
+        Ok(())
+    }
+
+    fn into_guess(request: MultiWordGuessRequest) -> String {
+        request.guess
+    }
+
+    fn day(&self) -> NaiveDate {
+        self.day
+    }
+
+    /// The feedback of every board, `None` for the boards solved before the guess
+    fn last_result(&self) -> Option<Vec<Option<WordResult>>> {
+        let latest = self.guesses.len().checked_sub(1)?;
+        Some(self.boards.iter().map(|board| board.guesses.get(latest).cloned()).collect())
+    }
+
+    fn restore(mut self, guess: String, feedback: Vec<Option<WordResult>>) -> Self {
+        for (board, result) in self.boards.iter_mut().zip(feedback) {
+            if let Some(result) = result {
+                *board = board.clone().restore(guess.clone(), result);
+            }
+        }
+        self.guesses.push(guess);
+        self.outcome = self.evaluate();
+        self
+    }
+
+    /// The answers are never serialized, so it is safe to send the state as is
+    fn view(&self) -> MultiWordGuess {
+        self.clone()
+    }
+
+    fn guess_response(&self) -> MultiWordGuessResponse {
+        MultiWordGuessResponse {
+            game_status: self.clone(),
+        }
+    }
+
+    fn resume(self) -> Self {
+        MultiWordGuess::resume(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+
+    fn day() -> NaiveDate {
+        NaiveDate::from_ymd_opt(2025, 1, 1).unwrap()
+    }
+
+    fn setup(answers: &[&str]) -> MultiWordGuess {
+        let mut game = MultiWordGuess::new(day(), answers.len(), LETTERS, answers.len() + EXTRA_GUESSES).unwrap();
+        for (board, answer) in game.boards.iter_mut().zip(answers) {
+            board.answer = answer.to_string();
+        }
+        game
+    }
+
+    #[test]
+    fn test_new_picks_different_answers() {
+        for boards in BOARD_COUNTS.clone() {
+            let game = MultiWordGuess::new(day(), boards, LETTERS, boards + EXTRA_GUESSES).unwrap();
+            let answers: HashSet<&String> = game.boards.iter().map(|board| &board.answer).collect();
+            assert_eq!(answers.len(), boards);
+        }
+
+        assert!(matches!(MultiWordGuess::new(day(), 1, LETTERS, 6), Err(GameError::InvalidVariant(_))));
+        assert!(matches!(MultiWordGuess::new(day(), 9, LETTERS, 14), Err(GameError::InvalidVariant(_))));
+        assert!(matches!(MultiWordGuess::new(day(), 4, 12, 9), Err(GameError::InvalidVariant(_))));
+    }
+
+    #[test]
+    fn test_guess_scores_every_board() {
+        let game = setup(&["crane", "slate"]).guess("crane").unwrap();
+        assert_eq!(game.guesses, ["crane"]);
+        assert_eq!(game.boards[0].outcome, Outcome::Won);
+        assert_eq!(game.boards[1].outcome, Outcome::InProgress);
+        assert_eq!(game.boards[1].guesses.len(), 1);
+        assert_eq!(game.outcome, Outcome::InProgress);
+
+        // A solved board stops taking guesses
+        let game = game.guess("slate").unwrap();
+        assert_eq!(game.boards[0].guesses.len(), 1);
+        assert_eq!(game.boards[1].guesses.len(), 2);
+        assert_eq!(game.outcome, Outcome::Won);
+        assert!(matches!(game.guess("orate"), Err(GameError::GameOver)));
+
+        let last = serde_json::json!(game.last_result());
+        assert!(last[0].is_null());
+        assert_eq!(last[1]["letters"][0]["value"], "s");
+    }
+
+    #[test]
+    fn test_shared_guess_budget() {
+        let mut game = setup(&["crane", "slate"]);
+        game.maximum_guesses = 2;
+        game.boards.iter_mut().for_each(|board| *board = board.clone().with_maximum_guesses(2).unwrap());
+
+        let game = game.guess("crane").unwrap().guess("orate").unwrap();
+        assert_eq!(game.outcome, Outcome::Lost);
+        assert_eq!(game.boards[0].outcome, Outcome::Won);
+        assert_eq!(game.boards[1].outcome, Outcome::Lost);
+        assert_eq!(game.boards[1].solution.as_deref(), Some("slate"));
+        assert!(matches!(game.guess("slate"), Err(GameError::MaximumGuesses)));
+    }
+
+    #[test]
+    fn test_guess_is_checked_once() {
+        let game = setup(&["crane", "slate"]);
+        assert!(matches!(game.guess("zzzzz"), Err(GameError::NotInWordList(_))));
+        assert!(matches!(game.guess("cranes"), Err(GameError::InvalidGuess(_))));
+
+        let game = game.guess("crane").unwrap();
+        assert!(matches!(game.guess("crane"), Err(GameError::DuplicateGuess)));
+    }
+
+    #[test]
+    fn test_replay_restores_answers() {
+        let game = MultiWordGuess::new(day(), 4, 6, 9).unwrap();
+        let stored: MultiWordGuess = serde_json::from_str(&serde_json::to_string(&game).unwrap()).unwrap();
+        assert!(stored.boards.iter().all(|board| board.answer.is_empty()));
+
+        let answer = game.boards[2].answer.clone();
+        let played = game.guess(&answer).unwrap();
+        let replayed = stored
+            .replay(vec![(serde_json::json!(answer), serde_json::json!(played.last_result()))])
+            .unwrap();
+        assert_eq!(replayed.boards[2].outcome, Outcome::Won);
+        assert_eq!(replayed.boards[2].guesses.len(), 1);
+        assert_eq!(serde_json::json!(replayed), serde_json::json!(played));
+        assert_eq!(replayed.boards[0].answer, game.boards[0].answer);
+    }
+}
@@ -0,0 +1,385 @@
+use schemars::{
+    gen::{SchemaGenerator, SchemaSettings},
+    JsonSchema,
+};
+use serde_json::{json, Map, Value};
+
+use crate::{
+    errors::ErrorBody,
+    models::TokenResponse,
+    registry::{GameMetadata, Registry},
+    token,
+};
+
+/// The explorer served at `/docs`, it reads `/openapi.json`
+pub static EXPLORER: &str = include_str!("../static/explorer.html");
+
+/// The error statuses an endpoint can answer with, and their codes
+static ERROR_STATUSES: [(u16, &str); 8] = [
+    (400, "The request isn't valid: `invalid_request` or `invalid_variant`"),
+    (401, "The session doesn't match a player: `missing_token`, `malformed_token`, `unknown_session`, `invalid_token` or `token_replayed`"),
+    (404, "Not found: `unknown_game` or `unknown_schema`"),
+    (409, "The guess can't be made now: `maximum_guesses`, `game_over`, `guess_conflict` or `variant_mismatch`"),
+    (422, "The guess isn't accepted: `invalid_guess`, `not_in_word_list`, `not_on_board`, `wrong_word_count` or `duplicate_guess`"),
+    (500, "Something went wrong on the server: `database_error`, `corrupted_game` or `internal_error`"),
+    (502, "The puzzle couldn't be fetched: `network_error` or `puzzle_parse_error`"),
+    (503, "Try again later: `puzzle_unavailable` or `database_unavailable`"),
+];
+
+/// The OpenAPI 3 document of every route of the server
+/// ### Arguments
+/// * `registry` - The hosted games, each one adds its routes
+pub fn document(registry: &Registry) -> Value {
+    let mut generator = SchemaSettings::openapi3().into_generator();
+    generator.subschema_for::<ErrorBody>();
+
+    let mut paths = Map::new();
+    let mut tags = Vec::new();
+    for game in registry.games() {
+        tags.push(json!({ "name": game.name, "description": game.description }));
+        paths.extend((game.paths)(&mut generator));
+    }
+
+    tags.push(json!({ "name": "Session", "description": "Players are tracked by a session, see `POST /token`" }));
+    paths.insert(
+        "/token".to_string(),
+        json!({
+            "post": {
+                "tags": ["Session"],
+                "summary": "Create a player",
+                "description": "Send the token back as `Authorization: Bearer <token>`, and replace it with the `X-Session-Token` header of every guess",
+                "operationId": "create_session",
+                "responses": with_errors(
+                    json!({
+                        "200": {
+                            "description": "The new player",
+                            "headers": session_headers(),
+                            "content": json_content(generator.subschema_for::<TokenResponse>()),
+                        }
+                    }),
+                    &[503],
+                ),
+            }
+        }),
+    );
+
+    tags.push(json!({ "name": "Documentation" }));
+    paths.insert(
+        "/openapi.json".to_string(),
+        json!({
+            "get": {
+                "tags": ["Documentation"],
+                "summary": "This document",
+                "operationId": "get_openapi",
+                "responses": { "200": { "description": "The OpenAPI document", "content": { "application/json": {} } } },
+            }
+        }),
+    );
+    paths.insert(
+        "/docs".to_string(),
+        json!({
+            "get": {
+                "tags": ["Documentation"],
+                "summary": "Explore and try the API",
+                "operationId": "get_docs",
+                "responses": { "200": { "description": "The API explorer", "content": { "text/html": {} } } },
+            }
+        }),
+    );
+
+    json!({
+        "openapi": "3.0.3",
+        "info": {
+            "title": "Word Games",
+            "version": env!("CARGO_PKG_VERSION"),
+            "description": "Word games for people and LLMs. Every error is an `ErrorBody` with a stable `code`",
+        },
+        "tags": tags,
+        "paths": paths,
+        "components": {
+            "schemas": generator.take_definitions(),
+            "securitySchemes": {
+                "bearer": {
+                    "type": "http",
+                    "scheme": "bearer",
+                    "description": "`<stale_token>.<fresh_token>`, from `POST /token` or the `X-Session-Token` header of the latest guess",
+                },
+                "stale_token": { "type": "apiKey", "in": "cookie", "name": "stale_token" },
+                "fresh_token": { "type": "apiKey", "in": "cookie", "name": "fresh_token" },
+            },
+        },
+    })
+}
+
+/// The paths of a registered game
+/// * `/{ROUTE}` - Get the game, make a guess
+/// * `/{ROUTE}/schema` and `/{ROUTE}/schema/{kind}` - The JSON schemas of the game
+pub fn game_paths<G: GameMetadata>(generator: &mut SchemaGenerator) -> Map<String, Value> {
+    let state = generator.subschema_for::<G::View>();
+    let request = generator.subschema_for::<G::Request>();
+    let response = generator.subschema_for::<G::Response>();
+    let options = query_parameters::<G::Options>();
+    let tags = json!([G::NAME]);
+
+    let mut paths = Map::new();
+    paths.insert(
+        format!("/{}", G::ROUTE),
+        json!({
+            "get": {
+                "tags": tags,
+                "summary": format!("Get today's {} game", G::NAME),
+                "description": "A request without a session gets a new game, which is only saved by its first guess",
+                "operationId": format!("get_{}", G::KEY),
+                "security": session_security(),
+                "parameters": options,
+                "responses": with_errors(
+                    json!({ "200": { "description": "The game", "content": json_content(state) } }),
+                    &[400, 401, 409, 500, 502, 503],
+                ),
+            },
+            "post": {
+                "tags": tags,
+                "summary": format!("Make a {} guess", G::NAME),
+                "description": "Every guess rotates the session, the new token is in the `X-Session-Token` header and the cookies",
+                "operationId": format!("guess_{}", G::KEY),
+                "security": session_security(),
+                "parameters": options,
+                "requestBody": { "required": true, "content": json_content(request) },
+                "responses": with_errors(
+                    json!({
+                        "200": {
+                            "description": "The game after the guess",
+                            "headers": session_headers(),
+                            "content": json_content(response),
+                        }
+                    }),
+                    &[400, 401, 409, 422, 500, 502, 503],
+                ),
+            },
+        }),
+    );
+
+    let json_schema = json!({ "type": "object", "description": "A JSON schema (draft 7)" });
+    paths.insert(
+        format!("/{}/schema", G::ROUTE),
+        json!({
+            "get": {
+                "tags": tags,
+                "summary": format!("The JSON schema of a {} guess", G::NAME),
+                "operationId": format!("get_{}_schema", G::KEY),
+                "responses": { "200": { "description": "The schema of the request", "content": json_content(json_schema.clone()) } },
+            }
+        }),
+    );
+    paths.insert(
+        format!("/{}/schema/{{kind}}", G::ROUTE),
+        json!({
+            "get": {
+                "tags": tags,
+                "summary": format!("A JSON schema of {}", G::NAME),
+                "operationId": format!("get_{}_schema_of", G::KEY),
+                "parameters": [{
+                    "name": "kind",
+                    "in": "path",
+                    "required": true,
+                    "description": "The `request`, the `response` to a guess, the `state` or an `error`",
+                    "schema": { "type": "string", "enum": ["request", "response", "state", "error"] },
+                }],
+                "responses": with_errors(
+                    json!({ "200": { "description": "The schema", "content": json_content(json_schema) } }),
+                    &[404],
+                ),
+            }
+        }),
+    );
+
+    paths.extend(G::paths(generator));
+    paths
+}
+
+/// The query parameters read into `T`, one per field
+pub fn query_parameters<T: JsonSchema>() -> Value {
+    let schema = SchemaSettings::openapi3()
+        .with(|settings| settings.inline_subschemas = true)
+        .into_generator()
+        .into_root_schema_for::<T>();
+    let Some(object) = schema.schema.object else {
+        return json!([]);
+    };
+    object
+        .properties
+        .into_iter()
+        .map(|(name, schema)| {
+            let description = schema
+                .clone()
+                .into_object()
+                .metadata
+                .and_then(|metadata| metadata.description);
+            let mut parameter = json!({
+                "name": name,
+                "in": "query",
+                "required": object.required.contains(&name),
+                "schema": schema,
+            });
+            if let Some(description) = description {
+                parameter["description"] = json!(description);
+            }
+            parameter
+        })
+        .collect()
+}
+
+/// A JSON body of `schema`
+pub fn json_content(schema: impl serde::Serialize) -> Value {
+    json!({ "application/json": { "schema": schema } })
+}
+
+/// A new player, a bearer token or both session cookies
+pub fn session_security() -> Value {
+    json!([{}, { "bearer": [] }, { "stale_token": [], "fresh_token": [] }])
+}
+
+/// The rotated session, sent with every guess
+fn session_headers() -> Value {
+    json!({
+        token::SESSION_HEADER: {
+            "description": "The new session token, send it with the next request",
+            "schema": { "type": "string" },
+        }
+    })
+}
+
+/// Add the error responses of `statuses`
+pub fn with_errors(mut responses: Value, statuses: &[u16]) -> Value {
+    for (status, description) in ERROR_STATUSES.iter().filter(|(status, _)| statuses.contains(status)) {
+        responses[status.to_string()] = json!({
+            "description": description,
+            "content": json_content(json!({ "$ref": "#/components/schemas/ErrorBody" })),
+        });
+    }
+    responses
+}
+
+
+#[cfg(test)]
+mod tests {
+    use actix_web::ResponseError;
+
+    use super::*;
+    use crate::{
+        errors::{ApiError, DatabaseError, GameError, SessionError},
+        registry,
+    };
+
+    /// One error of every kind, whatever the details
+    fn every_error() -> Vec<ApiError> {
+        let text = || "text".to_string();
+        let mut errors = vec![
+            ApiError::InvalidRequest(text()),
+            ApiError::UnknownGame(text()),
+            ApiError::UnknownSchema(text()),
+            ApiError::Internal(text()),
+        ];
+        errors.extend(
+            [
+                GameError::MaximumGuesses,
+                GameError::GameOver,
+                GameError::InvalidGuess(text()),
+                GameError::NotInWordList(text()),
+                GameError::NotOnBoard(text()),
+                GameError::WrongWordCount { expected: 4, got: 3 },
+                GameError::DuplicateGuess,
+                GameError::NetworkError(text()),
+                GameError::PuzzleUnavailable(text()),
+                GameError::PuzzleParseError(text()),
+                GameError::VariantMismatch(text()),
+                GameError::InvalidVariant(text()),
+            ]
+            .map(ApiError::from),
+        );
+        errors.extend(
+            [
+                DatabaseError::FromSQLError(rusqlite::Error::InvalidQuery),
+                DatabaseError::GameStatusParseError(text()),
+                DatabaseError::PoolError(text()),
+                DatabaseError::MigrationError(text()),
+                DatabaseError::GuessConflict,
+            ]
+            .map(ApiError::from),
+        );
+        errors.extend(
+            [
+                SessionError::MissingToken("fresh"),
+                SessionError::MalformedToken,
+                SessionError::UnknownSession,
+                SessionError::InvalidToken,
+                SessionError::TokenReplayed,
+            ]
+            .map(ApiError::from),
+        );
+        errors
+    }
+
+    /// Every `$ref` in `value`
+    fn references(value: &Value, found: &mut Vec<String>) {
+        match value {
+            Value::Object(fields) => {
+                if let Some(Value::String(reference)) = fields.get("$ref") {
+                    found.push(reference.clone());
+                }
+                fields.values().for_each(|value| references(value, found));
+            }
+            Value::Array(values) => values.iter().for_each(|value| references(value, found)),
+            _ => {}
+        }
+    }
+
+    #[test]
+    fn test_document_every_route() {
+        let registry = registry::games();
+        let document = document(&registry);
+
+        assert_eq!(document["openapi"], "3.0.3");
+        for game in registry.games() {
+            let route = format!("/{}", game.route);
+            assert!(document["paths"][&route]["get"].is_object());
+            assert!(document["paths"][&route]["post"]["requestBody"].is_object());
+            assert!(document["paths"][format!("{}/schema/{{kind}}", route)]["get"].is_object());
+        }
+        for path in ["/token", "/openapi.json", "/docs", "/wordguess/hint"] {
+            assert!(document["paths"][path].is_object(), "{} isn't documented", path);
+        }
+
+        let guess = &document["paths"]["/wordguess"]["post"];
+        let hard_mode = guess["parameters"].as_array().unwrap().iter().find(|parameter| parameter["name"] == "hard_mode");
+        assert_eq!(hard_mode.unwrap()["in"], "query");
+        assert!(guess["responses"]["200"]["headers"][token::SESSION_HEADER].is_object());
+        assert_eq!(guess["responses"]["422"]["content"]["application/json"]["schema"]["$ref"], "#/components/schemas/ErrorBody");
+        assert!(document["components"]["securitySchemes"]["bearer"].is_object());
+
+        // Every code is documented under its status
+        for error in every_error() {
+            let status = error.status_code().as_u16();
+            let description = ERROR_STATUSES.iter().find(|(code, _)| *code == status).map(|(_, description)| *description);
+            assert!(
+                description.is_some_and(|description| description.contains(&format!("`{}`", error.code()))),
+                "`{}` isn't documented under {}",
+                error.code(),
+                status
+            );
+        }
+    }
+
+    #[test]
+    fn test_references_resolve() {
+        let document = document(&registry::games());
+
+        let mut found = Vec::new();
+        references(&document, &mut found);
+        assert!(!found.is_empty());
+        for reference in found {
+            let name = reference.strip_prefix("#/components/schemas/").unwrap();
+            assert!(document["components"]["schemas"][name].is_object(), "{} is missing", reference);
+        }
+    }
+}
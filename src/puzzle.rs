@@ -0,0 +1,507 @@
+use std::{
+    collections::hash_map::DefaultHasher,
+    fs,
+    future::Future,
+    hash::{Hash, Hasher},
+    path::PathBuf,
+    pin::Pin,
+};
+
+use chrono::NaiveDate;
+use html_escape::decode_html_entities;
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde::Deserialize;
+
+use crate::{
+    db,
+    errors::GameError,
+    groupthem::{GROUPS, ITEMS_PER_GROUP},
+    models::{Group, Ranking, Word},
+};
+
+/// The groups of a GroupThem puzzle and its shuffled words
+pub type Puzzle = (Vec<Group>, Vec<Word>);
+
+pub type PuzzleFuture<'a> = Pin<Box<dyn Future<Output = Result<Puzzle, GameError>> + 'a>>;
+
+/// A source of GroupThem puzzles
+pub trait PuzzleProvider: Send + Sync {
+    /// Get the puzzle published on `day`
+    /// ### Arguments
+    /// * `pool` - The database connections, used by providers that cache puzzles
+    /// * `day` - The date of the puzzle
+    fn get_puzzle<'a>(&'a self, pool: &'a db::Pool, day: NaiveDate) -> PuzzleFuture<'a>;
+}
+
+/// Select a puzzle provider by name
+/// ### Arguments
+/// * `source` - One of `scraper`, `directory` or `cache`
+/// * `directory` - The puzzle directory, required by the `directory` source
+pub fn provider(
+    source: &str,
+    directory: Option<PathBuf>,
+) -> Result<Box<dyn PuzzleProvider>, String> {
+    match source.to_lowercase().as_str() {
+        "scraper" => Ok(Box::new(ConnectionsAnswer)),
+        "cache" => Ok(Box::new(CacheProvider)),
+        "directory" => match directory {
+            Some(path) => Ok(Box::new(DirectoryProvider { path })),
+            None => Err("The `directory` puzzle source needs a puzzle directory".to_string()),
+        },
+        _ => Err(format!(
+            "Unknown puzzle source `{}`, expected `scraper`, `directory` or `cache`",
+            source
+        )),
+    }
+}
+
+/// Shuffle the words so the groups aren't given away by the order
+fn shuffle(words: &mut [Word]) {
+    fn calculate_hash<T: Hash>(t: &T) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        t.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    words.sort_by_key(calculate_hash);
+}
+
+lazy_static! {
+    static ref TAGS: Regex = Regex::new(r"<[^>]*>").unwrap();
+    static ref ANSWER_HEADING: Regex =
+        Regex::new(r#"<h2[^>]*\bid=["']?what-is-the-answer-to-connections-today["']?[^>]*>"#).unwrap();
+    static ref LIST_ITEM: Regex = Regex::new(r"(?s)<li[^>]*>(.*?)</li>").unwrap();
+    static ref FONT: Regex = Regex::new(r"(?s)<font[^>]*>(.*?)</font>").unwrap();
+}
+
+/// Parse the answer section of a https://www.connections-answer.com post
+/// ### Arguments
+/// * `html` - The page of the puzzle
+/// ### Errors
+/// Returns a `GameError::PuzzleParseError` naming the part of the page that was missing
+///
+/// The groups are the `<li>` items of the first list after the answer heading.\
+/// The words of a group are inside a `<font>` element, and any text before it is the group's name
+/// ```html
+/// <h2 id=what-is-the-answer-to-connections-today>...</h2>
+/// <ul>
+///   <li><strong>Desserts:</strong> <font color=green>cake, pie, pudding, cookie</font></li>
+/// </ul>
+/// ```
+pub fn parse_puzzle(html: &str) -> Result<Puzzle, GameError> {
+    fn remove_unwanted_characters(input: &str) -> String {
+        // Get rid of dem pesky emojis
+        input.chars()
+            .filter(|&c| c.is_ascii() || (c as u32) <= 0xFFFF)
+            .collect()
+    }
+
+    /// Strip the tags and entities from an HTML fragment
+    fn text(fragment: &str) -> String {
+        let text = TAGS.replace_all(fragment, "");
+        decode_html_entities(remove_unwanted_characters(&text).trim()).to_string()
+    }
+
+    // Everything after the answer heading
+    let answer = ANSWER_HEADING
+        .find(html)
+        .map(|m| &html[m.end()..])
+        .ok_or(GameError::PuzzleParseError(
+            "the answer heading `what-is-the-answer-to-connections-today` is missing".to_string(),
+        ))?;
+
+    // The first <ul> element after the heading
+    let start = answer.find("<ul").ok_or(GameError::PuzzleParseError(
+        "the list of answers after the answer heading is missing".to_string(),
+    ))?;
+    let end = answer[start..].find("</ul>").ok_or(GameError::PuzzleParseError(
+        "the list of answers is never closed with `</ul>`".to_string(),
+    ))?;
+    let list = &answer[start..start + end];
+
+    let mut groups = Vec::new();
+    let mut words = Vec::new();
+    for (i, item) in LIST_ITEM.captures_iter(list).enumerate() {
+        let item = item.get(1).map_or("", |m| m.as_str());
+        let font = FONT.captures(item).and_then(|c| c.get(1)).ok_or(
+            GameError::PuzzleParseError(format!("group {} has no `<font>` element with its words", i + 1)),
+        )?;
+
+        // The text before the words is the name of the group
+        let name = text(&item[..font.start()])
+            .trim_end_matches([':', '-', '–'])
+            .trim()
+            .to_string();
+
+        let group = Group {
+            name: if name.is_empty() { "Unknown".to_string() } else { name },
+            ranking: Ranking::from_index(i),
+        };
+
+        let group_words: Vec<Word> = text(font.as_str())
+            .split(',')
+            .map(|w| w.trim().to_lowercase())
+            .filter(|w| !w.is_empty())
+            .map(|text| Word {
+                text,
+                group: group.clone(),
+            })
+            .collect();
+
+        if group_words.len() != ITEMS_PER_GROUP {
+            return Err(GameError::PuzzleParseError(format!(
+                "group {} has {} words, expected {}",
+                i + 1,
+                group_words.len(),
+                ITEMS_PER_GROUP
+            )));
+        }
+
+        words.extend(group_words);
+        groups.push(group);
+    }
+
+    if groups.len() != GROUPS as usize {
+        return Err(GameError::PuzzleParseError(format!(
+            "found {} groups in the list of answers, expected {}",
+            groups.len(),
+            GROUPS
+        )));
+    }
+
+    shuffle(&mut words);
+    Ok((groups, words))
+}
+
+/// Scrapes the puzzle from https://www.connections-answer.com and caches it in `game_cache`
+pub struct ConnectionsAnswer;
+
+impl ConnectionsAnswer {
+    async fn scrape(&self, day: NaiveDate) -> Result<Puzzle, GameError> {
+        println!("*****************************************");
+        println!("Hey! You are using a third-party service to get the game state. This service could not be possible without the operation and maintenance of https://www.connections-answer.com . Please check them out and buy the author a coffee if this helps!");
+        println!("*****************************************");
+
+        let date_formatted = day.format("%B-%e-%Y").to_string().to_lowercase().replace("- ", "-").trim().to_string();
+
+        let url = format!("https://www.connections-answer.com/posts/nyt-connections-answer-hint-{}", date_formatted);
+
+        let response = reqwest::get(&url)
+        .await
+        .map_err(
+            |e| GameError::NetworkError(e.to_string())
+        )?;
+
+        if !response.status().is_success() {
+            return Err(GameError::PuzzleUnavailable(format!(
+                "{} responded with {}",
+                url,
+                response.status()
+            )));
+        }
+
+        let html = response
+        .text()
+        .await
+        .map_err(
+            |e| GameError::NetworkError(e.to_string())
+        )?;
+
+        parse_puzzle(&html)
+    }
+}
+
+impl PuzzleProvider for ConnectionsAnswer {
+    fn get_puzzle<'a>(&'a self, pool: &'a db::Pool, day: NaiveDate) -> PuzzleFuture<'a> {
+        Box::pin(async move {
+            if let Ok(cache) = db::run(pool, move |conn| db::get_game_cache::<Puzzle>(conn, day)).await {
+                return Ok(cache)
+            }
+
+            let puzzle = self.scrape(day).await?;
+
+            let cached = puzzle.clone();
+            let _ = db::run(pool, move |conn| db::store_game_cache::<Puzzle>(
+                conn,
+                &cached,
+                day
+            )).await.map_err(
+                |e| {
+                    eprintln!("Failed to save game to cache: {}", e)
+                }
+            );
+
+            Ok(puzzle)
+        })
+    }
+}
+
+/// Reads puzzles that were already stored in the `game_cache` table.\
+/// Never touches the network
+pub struct CacheProvider;
+
+impl PuzzleProvider for CacheProvider {
+    fn get_puzzle<'a>(&'a self, pool: &'a db::Pool, day: NaiveDate) -> PuzzleFuture<'a> {
+        Box::pin(async move {
+            db::run(pool, move |conn| db::get_game_cache::<Puzzle>(conn, day)).await.map_err(|e| {
+                GameError::PuzzleUnavailable(format!("No cached puzzle for {}: {}", day, e))
+            })
+        })
+    }
+}
+
+/// A puzzle file, in JSON or TOML.\
+/// Groups are listed from easiest to hardest
+/// ```toml
+/// [[groups]]
+/// name = "common desserts"
+/// words = ["cake", "pie", "pudding", "cookie"]
+/// ```
+#[derive(Debug, Deserialize)]
+struct PuzzleFile {
+    groups: Vec<PuzzleFileGroup>,
+}
+
+#[derive(Debug, Deserialize)]
+struct PuzzleFileGroup {
+    name: String,
+    words: Vec<String>,
+}
+
+impl PuzzleFile {
+    fn into_puzzle(self) -> Result<Puzzle, String> {
+        if self.groups.len() != GROUPS as usize {
+            return Err(format!("Expected {} groups, found {}", GROUPS, self.groups.len()));
+        }
+
+        let mut groups = Vec::new();
+        let mut words = Vec::new();
+        for (i, file_group) in self.groups.into_iter().enumerate() {
+            if file_group.words.len() != ITEMS_PER_GROUP {
+                return Err(format!(
+                    "Group `{}` needs {} words, found {}",
+                    file_group.name,
+                    ITEMS_PER_GROUP,
+                    file_group.words.len()
+                ));
+            }
+
+            let group = Group {
+                name: file_group.name,
+                ranking: Ranking::from_index(i),
+            };
+            words.extend(file_group.words.iter().map(|text| Word {
+                text: text.trim().to_lowercase(),
+                group: group.clone(),
+            }));
+            groups.push(group);
+        }
+
+        shuffle(&mut words);
+        Ok((groups, words))
+    }
+}
+
+/// Reads puzzles from a local directory.\
+/// The puzzle for a day is stored as `YYYY-MM-DD.json` or `YYYY-MM-DD.toml`
+pub struct DirectoryProvider {
+    pub path: PathBuf,
+}
+
+impl DirectoryProvider {
+    fn read(&self, day: NaiveDate) -> Result<Puzzle, GameError> {
+        let name = day.format("%Y-%m-%d").to_string();
+        let json = self.path.join(format!("{}.json", name));
+        let toml = self.path.join(format!("{}.toml", name));
+
+        let file: PuzzleFile = if let Ok(contents) = fs::read_to_string(&json) {
+            serde_json::from_str(&contents)
+                .map_err(|e| GameError::PuzzleUnavailable(format!("{}: {}", json.display(), e)))?
+        } else if let Ok(contents) = fs::read_to_string(&toml) {
+            toml::from_str(&contents)
+                .map_err(|e| GameError::PuzzleUnavailable(format!("{}: {}", toml.display(), e)))?
+        } else {
+            return Err(GameError::PuzzleUnavailable(format!(
+                "No puzzle for {} in {}",
+                day,
+                self.path.display()
+            )));
+        };
+
+        file.into_puzzle().map_err(GameError::PuzzleUnavailable)
+    }
+}
+
+impl PuzzleProvider for DirectoryProvider {
+    fn get_puzzle<'a>(&'a self, _pool: &'a db::Pool, day: NaiveDate) -> PuzzleFuture<'a> {
+        // Reading the file blocks, so it runs on the blocking thread pool
+        let provider = DirectoryProvider { path: self.path.clone() };
+        Box::pin(async move {
+            actix_web::web::block(move || provider.read(day))
+                .await
+                .map_err(|e| GameError::PuzzleUnavailable(e.to_string()))?
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn day() -> NaiveDate {
+        NaiveDate::from_ymd_opt(2025, 1, 1).unwrap()
+    }
+
+    fn temp_directory() -> PathBuf {
+        let path = std::env::temp_dir().join(format!("word_games_{}", crate::token::create_token()));
+        fs::create_dir_all(&path).unwrap();
+        path
+    }
+
+    static PUZZLE_JSON: &str = r#"{
+        "groups": [
+            {"name": "common desserts", "words": ["cake", "pie", "pudding", "cookie"]},
+            {"name": "items found in a stationery store", "words": ["pen", "notebook", "stapler", "envelope"]},
+            {"name": "famous detectives in literature", "words": ["holmes", "poirot", "marple", "spade"]},
+            {"name": "mythical creatures with human traits", "words": ["centaur", "mermaid", "minotaur", "sphinx"]}
+        ]
+    }"#;
+
+    #[test]
+    fn test_decode_html_entities() {
+        let result = decode_html_entities("&amp;").to_string();
+        let result_2 = decode_html_entities("pandora&rsquo;s box").to_string();
+        println!("{:?}", result);
+        println!("{:?}", result_2);
+        assert_eq!(result, "&".to_string());
+        assert_eq!(result_2, "pandora’s box".to_string());
+    }
+
+    #[test]
+    fn test_parse_puzzle_with_names() {
+        let (groups, words) = parse_puzzle(include_str!("../fixtures/connections-answer/complete.html")).unwrap();
+
+        let names: Vec<&str> = groups.iter().map(|g| g.name.as_str()).collect();
+        assert_eq!(names, vec![
+            "Common desserts",
+            "Items found in a stationery store",
+            "Famous detectives in literature",
+            "Mythical creatures with human traits",
+        ]);
+        assert_eq!(groups[3].ranking, Ranking::VeryDifficult);
+
+        assert_eq!(words.len(), 16);
+        assert!(words.iter().any(|w| w.text == "pandora’s box" && w.group == groups[3]));
+        assert!(words.iter().any(|w| w.text == "cake" && w.group == groups[0]));
+    }
+
+    #[test]
+    fn test_parse_puzzle_without_names() {
+        let (groups, words) = parse_puzzle(include_str!("../fixtures/connections-answer/no-names.html")).unwrap();
+
+        assert!(groups.iter().all(|g| g.name == "Unknown"));
+        assert_eq!(words.len(), 16);
+        // Emojis next to the words are dropped
+        assert!(words.iter().any(|w| w.text == "holmes"));
+    }
+
+    #[test]
+    fn test_parse_puzzle_missing_parts() {
+        let cases = [
+            (include_str!("../fixtures/connections-answer/missing-heading.html"), "heading"),
+            (include_str!("../fixtures/connections-answer/missing-list.html"), "list of answers"),
+            (include_str!("../fixtures/connections-answer/short-group.html"), "group 2 has 3 words"),
+        ];
+
+        for (html, expected) in cases {
+            match parse_puzzle(html) {
+                Err(GameError::PuzzleParseError(message)) => {
+                    assert!(message.contains(expected), "`{}` should mention `{}`", message, expected)
+                }
+                other => panic!("Expected a parse error mentioning `{}`, got {:?}", expected, other),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_directory_provider_json() {
+        let path = temp_directory();
+        fs::write(path.join("2025-01-01.json"), PUZZLE_JSON).unwrap();
+
+        let pool = db::initialize_pool(None, 1).unwrap();
+        let provider = DirectoryProvider { path: path.clone() };
+        let (groups, words) = provider.get_puzzle(&pool, day()).await.unwrap();
+        assert_eq!(groups.len(), 4);
+        assert_eq!(groups[0].name, "common desserts");
+        assert_eq!(groups[3].ranking, Ranking::VeryDifficult);
+        assert_eq!(words.len(), 16);
+        assert!(words.iter().any(|w| w.text == "poirot" && w.group == groups[2]));
+
+        // There is no puzzle for the next day
+        let next_day = day().succ_opt().unwrap();
+        assert!(matches!(
+            provider.get_puzzle(&pool, next_day).await,
+            Err(GameError::PuzzleUnavailable(_))
+        ));
+
+        fs::remove_dir_all(path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_directory_provider_toml() {
+        let path = temp_directory();
+        let file: serde_json::Value = serde_json::from_str(PUZZLE_JSON).unwrap();
+        fs::write(path.join("2025-01-01.toml"), toml::to_string(&file).unwrap()).unwrap();
+
+        let pool = db::initialize_pool(None, 1).unwrap();
+        let provider = DirectoryProvider { path: path.clone() };
+        let (groups, words) = provider.get_puzzle(&pool, day()).await.unwrap();
+        assert_eq!(groups.len(), 4);
+        assert_eq!(words.len(), 16);
+
+        fs::remove_dir_all(path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_directory_provider_rejects_incomplete_puzzle() {
+        let path = temp_directory();
+        fs::write(
+            path.join("2025-01-01.json"),
+            r#"{"groups": [{"name": "common desserts", "words": ["cake", "pie"]}]}"#,
+        ).unwrap();
+
+        let pool = db::initialize_pool(None, 1).unwrap();
+        let provider = DirectoryProvider { path: path.clone() };
+        assert!(matches!(
+            provider.get_puzzle(&pool, day()).await,
+            Err(GameError::PuzzleUnavailable(_))
+        ));
+
+        fs::remove_dir_all(path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_cache_provider() {
+        let pool = db::initialize_pool(None, 1).unwrap();
+        assert!(matches!(
+            CacheProvider.get_puzzle(&pool, day()).await,
+            Err(GameError::PuzzleUnavailable(_))
+        ));
+
+        let file: PuzzleFile = serde_json::from_str(PUZZLE_JSON).unwrap();
+        let puzzle = file.into_puzzle().unwrap();
+        db::store_game_cache(&pool.get().unwrap(), &puzzle, day()).unwrap();
+
+        assert_eq!(CacheProvider.get_puzzle(&pool, day()).await.unwrap(), puzzle);
+    }
+
+    #[test]
+    fn test_select_provider() {
+        assert!(provider("scraper", None).is_ok());
+        assert!(provider("Cache", None).is_ok());
+        assert!(provider("directory", Some(PathBuf::from("puzzles"))).is_ok());
+        assert!(provider("directory", None).is_err());
+        assert!(provider("carrier-pigeon", None).is_err());
+    }
+}
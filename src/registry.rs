@@ -0,0 +1,491 @@
+use std::{future::Future, pin::Pin};
+
+use actix_web::{web, HttpRequest, HttpResponse};
+use chrono::{Local, NaiveDate};
+use schemars::{gen::SchemaGenerator, schema::RootSchema, schema_for, JsonSchema};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::{
+    config::Config,
+    db,
+    errors::{ApiError, ErrorBody, GameError},
+    game::Game,
+    groupthem::GroupThem,
+    multiwordguess::MultiWordGuess,
+    openapi,
+    puzzle::PuzzleProvider,
+    session,
+    wordguess::WordGuess,
+};
+
+pub type GameFuture<'a, G> = Pin<Box<dyn Future<Output = Result<G, GameError>> + 'a>>;
+
+/// What a game can use to start a new board
+pub struct GameContext<'a> {
+    pub pool: &'a db::Pool,
+    pub provider: &'a dyn PuzzleProvider,
+    pub config: &'a Config,
+}
+
+/// What the server needs to host a `Game`.\
+/// A registered game is mounted at `/{ROUTE}`:
+/// * `GET /{ROUTE}` - The player's game of today, the query string picks the variant of a new game, ex. `?hard_mode=true`
+/// * `POST /{ROUTE}` - Make a guess
+/// * `GET /{ROUTE}/schema` - The schema of a guess
+/// * `GET /{ROUTE}/schema/{kind}` - The schema of the `request`, the `response` to a guess, the `state` or an `error`
+///
+/// Guesses are played with `Game::guess`. Games are stored as the state they started from and their guesses
+/// with the feedback they got, and rebuilt by restoring the feedback with `restore`
+pub trait GameMetadata:
+    Game<<Self as GameMetadata>::Guess, <Self as GameMetadata>::Clean, State = Self, GameError = GameError>
+    + Sized
+    + Clone
+    + Serialize
+    + DeserializeOwned
+    + Send
+    + 'static
+{
+    /// The route of the game, ex. `wordguess`
+    const ROUTE: &'static str;
+    /// The name the game is stored under, ex. `word_guess`
+    const KEY: &'static str;
+    /// The name shown in the API documentation, ex. `WordGuess`
+    const NAME: &'static str;
+    /// How the game is played, shown in the API documentation
+    const DESCRIPTION: &'static str;
+
+    /// The variant of the game, read from the query string of `GET` and `POST /{ROUTE}`.\
+    /// Only a new game is started with it, every field should be optional
+    type Options: DeserializeOwned + JsonSchema + 'static;
+    /// The body of `POST /{ROUTE}`
+    type Request: DeserializeOwned + JsonSchema + 'static;
+    /// A guess as stored in the `guesses` table, the `T` of `Game<T, U>`
+    type Guess: Clone + Serialize + DeserializeOwned + Send + 'static;
+    /// A guess checked by `Game::clean`, the `U` of `Game<T, U>`
+    type Clean;
+    /// The feedback of a guess as stored in the `guesses` table
+    type Feedback: Serialize + DeserializeOwned + Send + 'static;
+    /// The game as shown to players
+    type View: Serialize + JsonSchema;
+    /// The body answering a guess
+    type Response: Serialize + JsonSchema;
+
+    /// A new game of `day`
+    fn start<'a>(context: &'a GameContext<'a>, day: NaiveDate, options: &'a Self::Options) -> GameFuture<'a, Self>;
+
+    /// Check that a game that was already started is the variant asked for
+    /// ### Errors
+    /// Returns a `GameError::VariantMismatch` naming the variant of the game
+    fn check_options(&self, _options: &Self::Options) -> Result<(), GameError> {
+        Ok(())
+    }
+
+    /// The guess of a request
+    fn into_guess(request: Self::Request) -> Self::Guess;
+
+    /// The day of the puzzle
+    fn day(&self) -> NaiveDate;
+
+    /// The feedback of the latest guess, `None` before the first guess
+    fn last_result(&self) -> Option<Self::Feedback>;
+
+    /// Apply a stored guess with the feedback it got when it was made.\
+    /// Nothing is checked or scored again, so a game loads the same after its word list or rules change
+    fn restore(self, guess: Self::Guess, feedback: Self::Feedback) -> Self;
+
+    /// The game as shown to players
+    fn view(&self) -> Self::View;
+
+    /// The body answering a guess
+    fn guess_response(&self) -> Self::Response;
+
+    /// Mount the routes only this game has, ex. `GET /wordguess/hint`
+    fn configure(_cfg: &mut web::ServiceConfig) {}
+
+    /// The OpenAPI paths of the routes added by `configure`
+    fn paths(_generator: &mut SchemaGenerator) -> Map<String, Value> {
+        Map::new()
+    }
+
+    /// Restore what isn't stored of a game read from the database
+    fn resume(self) -> Self {
+        self
+    }
+
+    /// Rebuild a game by restoring guesses on the state it started from
+    /// ### Arguments
+    /// * `guesses` - The guesses and their feedback as stored, oldest first
+    /// ### Errors
+    /// Returns a `serde_json::Error` if a guess or its feedback isn't a `Guess` or a `Feedback`
+    fn replay(self, guesses: Vec<(Value, Value)>) -> Result<Self, serde_json::Error> {
+        let mut state = self.resume();
+        for (guess, feedback) in guesses {
+            state = state.restore(serde_json::from_value(guess)?, serde_json::from_value(feedback)?);
+        }
+        Ok(state)
+    }
+}
+
+/// The options of a game without variants
+#[derive(Debug, Default, Deserialize, JsonSchema)]
+pub struct NoOptions {}
+
+/// A registered game
+#[derive(Clone, Copy)]
+pub struct Entry {
+    pub route: &'static str,
+    pub key: &'static str,
+    pub name: &'static str,
+    pub description: &'static str,
+    /// The OpenAPI paths of the game, see `openapi::game_paths`
+    pub paths: fn(&mut SchemaGenerator) -> Map<String, Value>,
+    mount: fn(&mut web::ServiceConfig),
+}
+
+/// The games hosted by the server
+#[derive(Clone, Default)]
+pub struct Registry {
+    games: Vec<Entry>,
+}
+
+impl Registry {
+    /// Host `G` at `/{G::ROUTE}`
+    /// ### Panics
+    /// If another game uses the same route or storage key
+    pub fn register<G: GameMetadata>(mut self) -> Self {
+        assert!(
+            !self.games.iter().any(|game| game.route == G::ROUTE || game.key == G::KEY),
+            "A game is already registered at /{} or as `{}`",
+            G::ROUTE,
+            G::KEY
+        );
+
+        self.games.push(Entry {
+            route: G::ROUTE,
+            key: G::KEY,
+            name: G::NAME,
+            description: G::DESCRIPTION,
+            paths: openapi::game_paths::<G>,
+            mount: mount::<G>,
+        });
+        self
+    }
+
+    /// Every registered game, in the order they were registered
+    pub fn games(&self) -> &[Entry] {
+        &self.games
+    }
+
+    /// Mount the routes of every game
+    pub fn configure(&self, cfg: &mut web::ServiceConfig) {
+        for game in &self.games {
+            (game.mount)(cfg);
+        }
+    }
+}
+
+/// Every game hosted by the server. Register a new game here
+pub fn games() -> Registry {
+    Registry::default()
+        .register::<WordGuess>()
+        .register::<MultiWordGuess>()
+        .register::<GroupThem>()
+}
+
+fn mount<G: GameMetadata>(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::resource(format!("/{}", G::ROUTE))
+            // POST /{ROUTE}
+            // Make a guess
+            .route(web::post().to(make_guess::<G>))
+            // GET /{ROUTE}
+            // Get the current state of the game
+            .route(web::get().to(get_state::<G>)),
+    )
+    .service(
+        web::resource(format!("/{}/schema", G::ROUTE))
+            // GET /{ROUTE}/schema
+            // Get the schema used for POST requests
+            .route(web::get().to(get_schema::<G>)),
+    )
+    .service(
+        web::resource(format!("/{}/schema/{{kind}}", G::ROUTE))
+            // GET /{ROUTE}/schema/{kind}
+            // Get the schema of the request, the response, the state or an error
+            // Ex. GET /wordguess/schema/response
+            .route(web::get().to(get_schema_of::<G>)),
+    );
+    G::configure(cfg);
+}
+
+/// The schemas of a game, by kind
+/// ### Arguments
+/// * `kind` - `request`, `response`, `state` or `error`
+pub fn schema<G: GameMetadata>(kind: &str) -> Option<RootSchema> {
+    match kind {
+        "request" => Some(schema_for!(G::Request)),
+        "response" => Some(schema_for!(G::Response)),
+        "state" => Some(schema_for!(G::View)),
+        "error" => Some(schema_for!(ErrorBody)),
+        _ => None,
+    }
+}
+
+/// The player's game of `day`, or a new one of the variant in `options`
+/// ### Returns
+/// The player, the game and the number of stored guesses it was rebuilt from
+pub async fn load_or_start<G: GameMetadata>(
+    req: &HttpRequest,
+    pool: &db::Pool,
+    provider: &dyn PuzzleProvider,
+    config: &Config,
+    day: NaiveDate,
+    options: &G::Options,
+) -> Result<(crate::models::User, G, usize), ApiError> {
+    let (user, game) = session::load_game::<G>(req, pool, day).await?;
+    let (game, saved_guesses) = match game {
+        Some((game, saved_guesses)) => {
+            game.check_options(options)?;
+            (game, saved_guesses)
+        }
+        None => {
+            let context = GameContext {
+                pool,
+                provider,
+                config,
+            };
+            (G::start(&context, day, options).await?, 0)
+        }
+    };
+    Ok((user, game, saved_guesses))
+}
+
+async fn get_state<G: GameMetadata>(
+    options: web::Query<G::Options>,
+    req: HttpRequest,
+    provider: web::Data<dyn PuzzleProvider>,
+    pool: web::Data<db::Pool>,
+    config: web::Data<Config>,
+) -> Result<HttpResponse, ApiError> {
+    let today = Local::now().date_naive();
+    let (_, game, _) = load_or_start::<G>(&req, &pool, provider.as_ref(), &config, today, &options).await?;
+
+    Ok(HttpResponse::Ok().json(game.view()))
+}
+
+async fn make_guess<G: GameMetadata>(
+    options: web::Query<G::Options>,
+    request: web::Json<G::Request>,
+    req: HttpRequest,
+    provider: web::Data<dyn PuzzleProvider>,
+    pool: web::Data<db::Pool>,
+    config: web::Data<Config>,
+) -> Result<HttpResponse, ApiError> {
+    let guess = G::into_guess(request.into_inner());
+
+    let today = Local::now().date_naive();
+    let (user, before, saved_guesses) =
+        load_or_start::<G>(&req, &pool, provider.as_ref(), &config, today, &options).await?;
+
+    // Make a guess
+    let after = before.guess(guess.clone())?;
+    let body = after.guess_response();
+
+    // Store the guess
+    let player = user.clone();
+    let fresh_token = db::run(&pool, move |conn| {
+        db::record_guess(conn, &player, &before, saved_guesses, &guess, &after)
+    })
+    .await?;
+
+    let mut response = HttpResponse::Ok().json(body);
+
+    session::set_session(&mut response, &user.stale_token, &fresh_token);
+
+    Ok(response)
+}
+
+/// Any route that isn't served, named after the first segment of its path, ex. `chess` for `/chess/schema`
+pub async fn unknown_game(req: HttpRequest) -> Result<HttpResponse, ApiError> {
+    let game = req.path().trim_start_matches('/').split('/').next().unwrap_or_default();
+    Err(ApiError::UnknownGame(game.to_string()))
+}
+
+async fn get_schema<G: GameMetadata>() -> HttpResponse {
+    HttpResponse::Ok().json(schema_for!(G::Request))
+}
+
+async fn get_schema_of<G: GameMetadata>(kind: web::Path<String>) -> Result<HttpResponse, ApiError> {
+    let kind = kind.into_inner();
+    match schema::<G>(&kind) {
+        Some(schema) => Ok(HttpResponse::Ok().json(schema)),
+        None => Err(ApiError::UnknownSchema(kind)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use actix_web::{http::StatusCode, test as actix_test, App};
+
+    use serde_json::json;
+
+    use super::*;
+    use crate::{puzzle::CacheProvider, token};
+
+    #[actix_web::test]
+    async fn test_mount_registered_games() {
+        let provider: Arc<dyn PuzzleProvider> = Arc::new(CacheProvider);
+        let app = actix_test::init_service(
+            App::new()
+                .app_data(web::Data::from(provider))
+                .app_data(web::Data::new(db::initialize_pool(None, 1).unwrap()))
+                .app_data(web::Data::new(Config::default()))
+                .configure(|cfg| games().configure(cfg))
+                .default_service(web::to(unknown_game)),
+        )
+        .await;
+
+        let response = actix_test::call_service(&app, actix_test::TestRequest::get().uri("/wordguess").to_request()).await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        // Routes that aren't served answer with an error body too
+        for request in [
+            actix_test::TestRequest::get().uri("/chess"),
+            actix_test::TestRequest::post().uri("/chess"),
+            actix_test::TestRequest::get().uri("/chess/schema/state"),
+        ] {
+            let response = actix_test::call_service(&app, request.to_request()).await;
+            assert_eq!(response.status(), StatusCode::NOT_FOUND);
+            let body: Value = actix_test::read_body_json(response).await;
+            assert_eq!(body["code"], "unknown_game");
+            assert_eq!(body["details"]["game"], "chess");
+        }
+
+        // The variant of a new game is read from the query string
+        let request = actix_test::TestRequest::get().uri("/wordguess?letters=8&hard_mode=true").to_request();
+        let body: Value = actix_test::call_and_read_body_json(&app, request).await;
+        assert_eq!(body["letters"], 8);
+        assert_eq!(body["hard_mode"], true);
+        let response = actix_test::call_service(&app, actix_test::TestRequest::get().uri("/wordguess?letters=12").to_request()).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let request = actix_test::TestRequest::post()
+            .uri("/wordguess")
+            .set_json(json!({ "guess": "crane" }))
+            .to_request();
+        let response = actix_test::call_service(&app, request).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let session = response.headers().get(token::SESSION_HEADER).unwrap().to_str().unwrap().to_string();
+        let body: Value = actix_test::read_body_json(response).await;
+        assert_eq!(body["game_status"]["guesses"].as_array().unwrap().len(), 1);
+
+        // The guess was stored for the session
+        let request = actix_test::TestRequest::get()
+            .uri("/wordguess")
+            .insert_header(("Authorization", format!("Bearer {}", session)))
+            .to_request();
+        let body: Value = actix_test::call_and_read_body_json(&app, request).await;
+        assert_eq!(body["guesses"].as_array().unwrap().len(), 1);
+
+        // Games can add their own routes
+        let request = actix_test::TestRequest::get()
+            .uri("/wordguess/hint?limit=2")
+            .insert_header(("Authorization", format!("Bearer {}", session)))
+            .to_request();
+        let hint: Value = actix_test::call_and_read_body_json(&app, request).await;
+        assert!(hint["remaining"].as_u64().unwrap() > 0);
+        assert_eq!(hint["suggestions"].as_array().unwrap().len(), 2);
+        let response = actix_test::call_service(&app, actix_test::TestRequest::get().uri("/wordguess/hint?limit=0").to_request()).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let request = actix_test::TestRequest::get().uri("/groupthem/schema").to_request();
+        let schema: Value = actix_test::call_and_read_body_json(&app, request).await;
+        assert_eq!(schema["title"], "GroupThemRequest");
+
+        // Starting a game goes through its own errors, there is no cached puzzle
+        let response = actix_test::call_service(&app, actix_test::TestRequest::get().uri("/groupthem").to_request()).await;
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+    }
+
+    #[actix_web::test]
+    async fn test_stored_game_is_stable() {
+        let pool = db::initialize_pool(None, 1).unwrap();
+        let puzzle = crate::puzzle::parse_puzzle(include_str!("../fixtures/connections-answer/complete.html")).unwrap();
+        let yesterday = Local::now().date_naive() - chrono::Duration::days(1);
+        db::store_game_cache(&pool.get().unwrap(), &puzzle, yesterday).unwrap();
+
+        let provider: Arc<dyn PuzzleProvider> = Arc::new(CacheProvider);
+        let app = actix_test::init_service(
+            App::new()
+                .app_data(web::Data::from(provider))
+                .app_data(web::Data::new(pool))
+                .app_data(web::Data::new(Config::default()))
+                .configure(|cfg| games().configure(cfg)),
+        )
+        .await;
+
+        // A correct guess, then a wrong one
+        let group = |index: usize| -> Vec<&String> {
+            puzzle.1.iter().filter(|w| w.group == puzzle.0[index]).map(|w| &w.text).collect()
+        };
+        let wrong = [&group(1)[..3], &group(2)[..1]].concat();
+        let mut session: Option<String> = None;
+        for guess in [group(0), wrong] {
+            let mut request = actix_test::TestRequest::post().uri("/groupthem").set_json(json!({ "guess": guess }));
+            if let Some(session) = &session {
+                request = request.insert_header(("Authorization", format!("Bearer {}", session)));
+            }
+            let response = actix_test::call_service(&app, request.to_request()).await;
+            assert_eq!(response.status(), StatusCode::OK);
+            session = Some(response.headers().get(token::SESSION_HEADER).unwrap().to_str().unwrap().to_string());
+        }
+
+        // Every read restores the game as it was stored, colors and word order included
+        let get = || {
+            actix_test::TestRequest::get()
+                .uri("/groupthem")
+                .insert_header(("Authorization", format!("Bearer {}", session.clone().unwrap())))
+                .to_request()
+        };
+        let first = actix_test::call_and_read_body(&app, get()).await;
+        let second = actix_test::call_and_read_body(&app, get()).await;
+        assert_eq!(first, second);
+        let body: Value = serde_json::from_slice(&first).unwrap();
+        assert_eq!(body["guesses"].as_array().unwrap().len(), 2);
+        assert!(body["guesses"][0]["color"].is_object());
+    }
+
+    #[test]
+    fn test_schemas() {
+        let word_guess = |kind: &str| serde_json::to_value(schema::<WordGuess>(kind).unwrap()).unwrap();
+
+        assert_eq!(word_guess("request")["title"], "WordGuessRequest");
+        assert_eq!(word_guess("response")["title"], "WordGuessResponse");
+        assert_eq!(word_guess("state")["title"], "WordGuess");
+        assert_eq!(word_guess("error")["title"], "ErrorBody");
+
+        // Nested types are described too, ex. the feedback of a letter
+        let response = word_guess("response");
+        assert!(response["definitions"]["WordResult"].is_object());
+        assert!(response["definitions"]["Condition"].is_object());
+
+        // Only what players see of a GroupThem game is described
+        let state = serde_json::to_value(schema::<GroupThem>("state").unwrap()).unwrap();
+        assert_eq!(state["title"], "GroupThemView");
+
+        let state = serde_json::to_value(schema::<MultiWordGuess>("state").unwrap()).unwrap();
+        assert_eq!(state["title"], "MultiWordGuess");
+        assert!(state["definitions"]["WordGuess"].is_object());
+
+        assert!(schema::<WordGuess>("answer").is_none());
+    }
+
+    #[test]
+    #[should_panic(expected = "already registered")]
+    fn test_reject_duplicate_games() {
+        games().register::<WordGuess>();
+    }
+}
@@ -0,0 +1,154 @@
+use actix_web::{
+    cookie::Cookie,
+    http::header::{self, HeaderName, HeaderValue},
+    HttpRequest, HttpResponse,
+};
+use chrono::NaiveDate;
+
+use crate::{
+    db,
+    errors::{DatabaseError, SessionError},
+    models::{User, UserRequest},
+    registry::GameMetadata,
+    token,
+};
+
+/// The tokens of the request.\
+/// An `Authorization: Bearer <stale_token>.<fresh_token>` header is used over the cookies.
+/// A request without any token is a new player
+/// ### Errors
+/// * `MalformedToken` - An `Authorization` header was sent, but isn't a bearer token of `<stale_token>.<fresh_token>`
+/// * `MissingToken` - Only one of the two cookies was sent
+pub fn session_request(req: &HttpRequest) -> Result<UserRequest, SessionError> {
+    let bearer = match req.headers().get(header::AUTHORIZATION) {
+        Some(value) => {
+            // The scheme is case-insensitive, ex. `bearer <token>`
+            let (scheme, token) = value
+                .to_str()
+                .ok()
+                .and_then(|value| value.trim().split_once(' '))
+                .ok_or(SessionError::MalformedToken)?;
+            if !scheme.eq_ignore_ascii_case("bearer") {
+                return Err(SessionError::MalformedToken);
+            }
+            Some(token)
+        }
+        None => None,
+    };
+
+    let (stale_token, fresh_token) = match bearer {
+        Some(bearer) => {
+            let (stale_token, fresh_token) =
+                token::parse_session_token(bearer).ok_or(SessionError::MalformedToken)?;
+            (Some(stale_token), Some(fresh_token))
+        }
+        None => (
+            req.cookie("stale_token").map(|cookie| cookie.value().to_string()),
+            req.cookie("fresh_token").map(|cookie| cookie.value().to_string()),
+        ),
+    };
+
+    match (stale_token, fresh_token) {
+        (Some(stale_token), Some(fresh_token)) => Ok(UserRequest::Tokens {
+            stale_token,
+            fresh_token,
+        }),
+        (Some(_), None) => Err(SessionError::MissingToken("fresh")),
+        (None, Some(_)) => Err(SessionError::MissingToken("stale")),
+        (None, None) => Ok(UserRequest::NewUser),
+    }
+}
+
+/// Hand the rotated tokens back as cookies, for browsers, and as the `X-Session-Token` header, for bots
+pub fn set_session(response: &mut HttpResponse, stale_token: &str, fresh_token: &str) {
+    let _ = response.add_cookie(
+        &Cookie::build("fresh_token", fresh_token.to_string()).path("/")
+        .http_only(true)
+        .finish()
+    );
+    let _ = response.add_cookie(
+        &Cookie::build("stale_token", stale_token.to_string()).path("/")
+        .http_only(true)
+        .finish()
+    );
+
+    if let (Ok(name), Ok(value)) = (
+        HeaderName::from_bytes(token::SESSION_HEADER.as_bytes()),
+        HeaderValue::from_str(&token::session_token(stale_token, fresh_token)),
+    ) {
+        response.headers_mut().insert(name, value);
+    }
+}
+
+/// Expire the session cookies, so the next request of the browser starts a new player.\
+/// The cookies are `http_only`, so a browser can't clear a session that is no longer accepted by itself
+pub fn clear_session(response: &mut HttpResponse) {
+    for name in ["fresh_token", "stale_token"] {
+        let _ = response.add_removal_cookie(&Cookie::build(name, "").path("/").finish());
+    }
+}
+
+/// The user making the request, and their game of `G` on `day` with its number of guesses when they started it
+pub async fn load_game<G: GameMetadata>(
+    req: &HttpRequest,
+    pool: &db::Pool,
+    day: NaiveDate,
+) -> Result<(User, Option<(G, usize)>), DatabaseError> {
+    let request = session_request(req)?;
+
+    db::run(pool, move |conn| {
+        let user = db::get_user(conn, request)?;
+        let game = match user.fresh_token {
+            Some(_) => db::get_game::<G>(conn, &user.stale_token, day)?,
+            None => None,
+        };
+        Ok((user, game))
+    })
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::test::TestRequest;
+
+    use super::*;
+
+    fn authorization(value: &str) -> Result<UserRequest, SessionError> {
+        session_request(&TestRequest::default().insert_header((header::AUTHORIZATION, value)).to_http_request())
+    }
+
+    #[test]
+    fn test_bearer_scheme_is_case_insensitive() {
+        for value in ["Bearer a.b", "bearer a.b", "BEARER a.b"] {
+            assert!(
+                matches!(authorization(value), Ok(UserRequest::Tokens { stale_token, fresh_token }) if stale_token == "a" && fresh_token == "b"),
+                "{}",
+                value
+            );
+        }
+    }
+
+    #[test]
+    fn test_bad_authorization_is_malformed() {
+        // A bad credential never plays as a new player
+        for value in ["Token a.b", "Basic YTpi", "Bearer", "Bearer ab", "a.b", ""] {
+            assert_eq!(authorization(value).err(), Some(SessionError::MalformedToken), "{}", value);
+        }
+
+        // Even next to valid cookies
+        let req = TestRequest::default()
+            .insert_header((header::AUTHORIZATION, "Token a.b"))
+            .cookie(Cookie::new("stale_token", "a"))
+            .cookie(Cookie::new("fresh_token", "b"))
+            .to_http_request();
+        assert_eq!(session_request(&req).err(), Some(SessionError::MalformedToken));
+
+        // Without any header, the cookies are used
+        let req = TestRequest::default()
+            .cookie(Cookie::new("stale_token", "a"))
+            .cookie(Cookie::new("fresh_token", "b"))
+            .to_http_request();
+        assert!(matches!(session_request(&req), Ok(UserRequest::Tokens { .. })));
+        assert!(matches!(session_request(&TestRequest::default().to_http_request()), Ok(UserRequest::NewUser)));
+    }
+}
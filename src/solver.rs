@@ -0,0 +1,367 @@
+use std::{
+    collections::{HashMap, HashSet},
+    ops::RangeInclusive,
+    sync::OnceLock,
+};
+
+use actix_web::{web, HttpRequest, HttpResponse};
+use chrono::Local;
+use lazy_static::lazy_static;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config::Config,
+    db,
+    dictionary::{dictionary_of, Dictionary, WORD_LENGTHS},
+    errors::{ApiError, GameError},
+    models::{HintQuery, WordGuessOptions},
+    puzzle::PuzzleProvider,
+    registry,
+    wordguess::WordGuess,
+};
+
+/// The number of suggestions and candidates in a hint by default
+pub static HINT_LIMIT: usize = 5;
+/// The number of suggestions and candidates a hint can show
+pub static HINT_LIMITS: RangeInclusive<usize> = 1..=100;
+
+lazy_static! {
+    /// The ranking of the first guess of every word length, with the word lists of `dictionary_of`.\
+    /// Each length is ranked once, without blocking hints of other lengths
+    static ref OPENINGS: HashMap<usize, OnceLock<Vec<RankedGuess>>> =
+        WORD_LENGTHS.clone().map(|letters| (letters, OnceLock::new())).collect();
+}
+
+/// A guess ranked by the solver
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, JsonSchema)]
+pub struct RankedGuess {
+    pub word: String,
+    /// The expected information of the guess in bits, how much its feedback is expected to narrow the candidates
+    pub entropy: f64,
+    /// Whether the guess can be the answer
+    pub candidate: bool,
+}
+
+/// What the solver knows about a game
+#[derive(Debug, Serialize, Deserialize, Clone, JsonSchema)]
+pub struct Hint {
+    /// The number of answers that match the feedback of every guess so far
+    pub remaining: usize,
+    /// The first answers that match, in answer list order
+    pub candidates: Vec<String>,
+    /// The best guesses, the most informative first
+    pub suggestions: Vec<RankedGuess>,
+}
+
+/// The longest word `pattern` scores
+const MAXIMUM_LETTERS: usize = 16;
+
+/// The feedback of `guess` against `answer` as a number, one base 3 digit per letter.\
+/// It follows the rules of `wordguess::score` without building a `WordResult`, since ranking scores every guess against every candidate
+/// ### Arguments
+/// * `guess` - The letters of the guess, at most `MAXIMUM_LETTERS`
+/// * `answer` - The letters of the answer, as long as the guess
+fn pattern(guess: &[char], answer: &[char]) -> usize {
+    // 0 is not found, 1 is missplaced and 2 is correct
+    let mut conditions = [0u8; MAXIMUM_LETTERS];
+    let mut unmatched = [None; MAXIMUM_LETTERS];
+
+    // First pass: consume the exact matches
+    for (i, letter) in answer.iter().enumerate() {
+        if guess.get(i) == Some(letter) {
+            conditions[i] = 2;
+        } else {
+            unmatched[i] = Some(*letter);
+        }
+    }
+
+    // Second pass: a letter is only misplaced while unmatched copies remain
+    for (i, letter) in guess.iter().enumerate() {
+        if conditions[i] == 2 {
+            continue;
+        }
+        if let Some(copy) = unmatched[..answer.len()].iter_mut().find(|copy| **copy == Some(*letter)) {
+            *copy = None;
+            conditions[i] = 1;
+        }
+    }
+
+    conditions[..guess.len()].iter().fold(0, |code, condition| code * 3 + *condition as usize)
+}
+
+/// The answers that match the feedback of every guess of `game`
+/// ### Arguments
+/// * `game` - The game so far
+/// * `answers` - The possible answers, ex. `Dictionary::answers`
+pub fn candidates<'a>(game: &WordGuess, answers: &'a [String]) -> Vec<&'a String> {
+    answers
+        .iter()
+        .filter(|answer| answer.chars().count() == game.letters && game.is_consistent(answer))
+        .collect()
+}
+
+/// The expected information of a guess, in bits.\
+/// The entropy of the feedback it would get over `candidates`, which are all equally likely to be the answer
+/// ### Arguments
+/// * `guess` - The lowercase guess
+/// * `candidates` - The answers left, as long as the guess
+pub fn entropy(guess: &str, candidates: &[&String]) -> f64 {
+    let guess: Vec<char> = guess.chars().collect();
+    let candidates: Vec<Vec<char>> = candidates.iter().map(|candidate| candidate.chars().collect()).collect();
+    Feedback::new(guess.len()).entropy(&guess, &candidates)
+}
+
+/// The number of candidates per feedback, reused across guesses so ranking doesn't allocate per guess
+struct Feedback {
+    counts: Vec<usize>,
+    patterns: Vec<usize>,
+}
+
+impl Feedback {
+    /// Room for every feedback of words of `letters` letters
+    fn new(letters: usize) -> Self {
+        Feedback {
+            counts: vec![0; 3usize.pow(letters.min(MAXIMUM_LETTERS) as u32)],
+            patterns: Vec::new(),
+        }
+    }
+
+    fn entropy(&mut self, guess: &[char], candidates: &[Vec<char>]) -> f64 {
+        self.patterns.clear();
+        self.patterns.extend(candidates.iter().map(|candidate| pattern(guess, candidate)));
+        for pattern in &self.patterns {
+            self.counts[*pattern] += 1;
+        }
+
+        // Every feedback is counted once, then reset for the next guess
+        let total = candidates.len() as f64;
+        let mut entropy = 0.0;
+        for pattern in &self.patterns {
+            let count = std::mem::take(&mut self.counts[*pattern]);
+            if count > 0 {
+                let p = count as f64 / total;
+                entropy -= p * p.log2();
+            }
+        }
+        entropy
+    }
+}
+
+/// Rank every guess that can still be played by expected information
+/// ### Arguments
+/// * `game` - The game so far. In hard mode, only guesses that use every hint are ranked
+/// * `dictionary` - The word lists of the game
+/// * `candidates` - The answers left, from `candidates`
+/// ### Returns
+/// The guesses, the most informative first. Ties go to the guesses that can be the answer, then to alphabetical order
+pub fn rank(game: &WordGuess, dictionary: &Dictionary, candidates: &[&String]) -> Vec<RankedGuess> {
+    let guessed: HashSet<String> = game.guesses.iter().map(|result| result.to_string().to_lowercase()).collect();
+    let possible: HashSet<&str> = candidates.iter().map(|candidate| candidate.as_str()).collect();
+    let letters: Vec<Vec<char>> = candidates.iter().map(|candidate| candidate.chars().collect()).collect();
+    let mut feedback = Feedback::new(game.letters);
+
+    let mut ranked: Vec<RankedGuess> = dictionary
+        .allowed()
+        .filter(|word| word.chars().count() == game.letters && game.letters <= MAXIMUM_LETTERS && !guessed.contains(*word))
+        .filter(|word| !game.hard_mode || game.check_hints(word).is_ok())
+        .map(|word| RankedGuess {
+            word: word.clone(),
+            entropy: feedback.entropy(&word.chars().collect::<Vec<char>>(), &letters),
+            candidate: possible.contains(word.as_str()),
+        })
+        .collect();
+
+    ranked.sort_by(|a, b| {
+        b.entropy
+            .total_cmp(&a.entropy)
+            .then(b.candidate.cmp(&a.candidate))
+            .then(a.word.cmp(&b.word))
+    });
+    ranked
+}
+
+/// A hint for the next guess of `game`
+/// ### Arguments
+/// * `game` - The game so far
+/// * `dictionary` - The word lists of the game
+/// * `limit` - The number of candidates and suggestions to show
+pub fn hint(game: &WordGuess, dictionary: &Dictionary, limit: usize) -> Hint {
+    let candidates = candidates(game, dictionary.answers());
+    let suggestions = if game.outcome.is_over() {
+        Vec::new()
+    } else if let Some(opening) = OPENINGS
+        .get(&game.letters)
+        .filter(|_| game.guesses.is_empty() && dictionary_of(game.letters).is_some_and(|shared| std::ptr::eq(shared, dictionary)))
+    {
+        // Every game of a length opens the same way
+        let ranked = opening.get_or_init(|| rank(game, dictionary, &candidates));
+        ranked.iter().take(limit).cloned().collect()
+    } else {
+        let mut ranked = rank(game, dictionary, &candidates);
+        ranked.truncate(limit);
+        ranked
+    };
+
+    Hint {
+        remaining: candidates.len(),
+        candidates: candidates.iter().take(limit).map(|candidate| candidate.to_string()).collect(),
+        suggestions,
+    }
+}
+
+/// The candidates left and the best guesses of the player's WordGuess game of today
+pub async fn get_hint(
+    query: web::Query<HintQuery>,
+    options: web::Query<WordGuessOptions>,
+    req: HttpRequest,
+    provider: web::Data<dyn PuzzleProvider>,
+    pool: web::Data<db::Pool>,
+    config: web::Data<Config>,
+) -> Result<HttpResponse, ApiError> {
+    let limit = query.limit.unwrap_or(HINT_LIMIT);
+    if !HINT_LIMITS.contains(&limit) {
+        return Err(ApiError::InvalidRequest(format!(
+            "`limit` must be from {} to {}",
+            HINT_LIMITS.start(),
+            HINT_LIMITS.end()
+        )));
+    }
+
+    let today = Local::now().date_naive();
+    let (_, game, _) =
+        registry::load_or_start::<WordGuess>(&req, &pool, provider.as_ref(), &config, today, &options).await?;
+    let dictionary = dictionary_of(game.letters)
+        .ok_or_else(|| GameError::InvalidVariant(format!("There are no words of {} letters", game.letters)))?;
+
+    // Ranking scores every allowed word against every candidate, so it runs on the blocking thread pool
+    let hint = web::block(move || hint(&game, dictionary, limit))
+        .await
+        .map_err(|e| ApiError::Internal(e.to_string()))?;
+
+    Ok(HttpResponse::Ok().json(hint))
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use super::*;
+    use crate::{dictionary::dictionary, game::Game, wordguess::score};
+
+    fn setup(answer: &str) -> WordGuess {
+        let mut game = WordGuess::new(NaiveDate::from_ymd_opt(2025, 1, 1).unwrap(), 6);
+        game.answer = answer.to_string();
+        game
+    }
+
+    /// The feedback of `wordguess::score` as a `pattern`
+    fn code(guess: &str, answer: &str) -> usize {
+        serde_json::to_value(score(guess, answer)).unwrap()["letters"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .fold(0, |code, letter| {
+                code * 3
+                    + match letter["condition"].as_str().unwrap() {
+                        "Correct" => 2,
+                        "Missplaced" => 1,
+                        _ => 0,
+                    }
+            })
+    }
+
+    fn letters(word: &str) -> Vec<char> {
+        word.chars().collect()
+    }
+
+    #[test]
+    fn test_pattern_matches_score() {
+        let words = ["tepee", "steep", "steer", "orate", "radar", "beats", "eerie", "crane", "llama", "hello"];
+        for guess in words {
+            for answer in words {
+                assert_eq!(pattern(&letters(guess), &letters(answer)), code(guess, answer), "{} against {}", guess, answer);
+            }
+        }
+    }
+
+    #[test]
+    fn test_pattern_matches_score_over_dictionary() {
+        use rand::{rngs::StdRng, seq::SliceRandom, SeedableRng};
+
+        let answers = dictionary().answers();
+        let mut words: Vec<&String> = dictionary().allowed().collect();
+        words.sort();
+
+        // Every word in the dictionary against a fixed sample of answers
+        let mut rng = StdRng::seed_from_u64(5);
+        let sample: Vec<&String> = answers.choose_multiple(&mut rng, 25).collect();
+        for answer in sample {
+            for guess in &words {
+                assert_eq!(pattern(&letters(guess), &letters(answer)), code(guess, answer), "`{}` against `{}`", guess, answer);
+            }
+        }
+
+        // Every answer against a fixed sample of words
+        let sample: Vec<&&String> = words.choose_multiple(&mut rng, 25).collect();
+        for guess in sample {
+            for answer in answers {
+                assert_eq!(pattern(&letters(guess), &letters(answer)), code(guess, answer), "`{}` against `{}`", guess, answer);
+            }
+        }
+    }
+
+    #[test]
+    fn test_candidates_match_every_feedback() {
+        let game = setup("irate").guess("crane").unwrap().guess("steer").unwrap();
+        let candidates = candidates(&game, dictionary().answers());
+        assert!(candidates.contains(&&"irate".to_string()));
+        assert!(!candidates.contains(&&"crane".to_string()));
+        assert!(candidates.len() < dictionary().answers().len());
+        assert!(candidates.iter().all(|candidate| game.is_consistent(candidate)));
+    }
+
+    #[test]
+    fn test_entropy() {
+        let words: Vec<String> = ["crane", "slate", "moist", "bingo"].iter().map(|word| word.to_string()).collect();
+        let candidates: Vec<&String> = words.iter().collect();
+
+        // A guess that tells every candidate apart is worth 2 bits, one that tells none apart is worth nothing
+        assert!((entropy("crane", &candidates) - 2.0).abs() < 1e-9);
+        assert_eq!(entropy("fuzzy", &candidates[..2]), 0.0);
+    }
+
+    #[test]
+    fn test_hint() {
+        let game = setup("irate").guess("crane").unwrap();
+        let hint = hint(&game, dictionary(), 3);
+        assert!(hint.remaining > 1);
+        assert_eq!(hint.suggestions.len(), 3);
+        assert!(hint.candidates.len() <= 3);
+        assert!(hint.suggestions.windows(2).all(|pair| pair[0].entropy >= pair[1].entropy));
+        assert!(hint.suggestions.iter().all(|suggestion| suggestion.word != "crane"));
+
+        // Following the hints narrows the game down to the answer, which is then the best guess
+        let mut game = game;
+        let mut hint = hint;
+        while hint.remaining > 1 {
+            game = game.guess(&hint.suggestions[0].word).unwrap();
+            hint = super::hint(&game, dictionary(), 3);
+        }
+        assert_eq!(hint.candidates, ["irate"]);
+        if !game.outcome.is_over() {
+            assert_eq!(hint.suggestions[0].word, "irate");
+            game = game.guess("irate").unwrap();
+        }
+        assert_eq!(game.outcome, crate::game::Outcome::Won);
+        assert!(super::hint(&game, dictionary(), 3).suggestions.is_empty());
+    }
+
+    #[test]
+    fn test_hint_in_hard_mode() {
+        let game = setup("irate").with_hard_mode(true).guess("beats").unwrap();
+        let hint = hint(&game, dictionary(), 10);
+        assert!(!hint.suggestions.is_empty());
+        assert!(hint.suggestions.iter().all(|suggestion| game.check_hints(&suggestion.word).is_ok()));
+    }
+}
@@ -1,5 +1,50 @@
+use std::{
+    collections::hash_map::DefaultHasher,
+    hash::{Hash, Hasher},
+};
+
 use uuid::Uuid;
 
+/// The response header holding the session token after it was rotated
+pub static SESSION_HEADER: &str = "X-Session-Token";
+
 pub fn create_token() -> String { 
     Uuid::new_v4().to_string()
-}
\ No newline at end of file
+}
+
+/// A short id of a token for the logs, which can't be turned back into the token
+pub fn fingerprint(token: &str) -> String {
+    let mut hasher = DefaultHasher::new();
+    token.hash(&mut hasher);
+    format!("{:016x}", hasher.finish())
+}
+
+/// The session token sent in `Authorization: Bearer <token>`, ex. `<stale_token>.<fresh_token>`
+pub fn session_token(stale_token: &str, fresh_token: &str) -> String {
+    format!("{}.{}", stale_token, fresh_token)
+}
+
+/// Split a session token into its stale and fresh tokens
+pub fn parse_session_token(token: &str) -> Option<(String, String)> {
+    let (stale_token, fresh_token) = token.trim().split_once('.')?;
+    if stale_token.is_empty() || fresh_token.is_empty() {
+        return None;
+    }
+    Some((stale_token.to_string(), fresh_token.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_session_token() {
+        let (stale_token, fresh_token) = (create_token(), create_token());
+        let token = session_token(&stale_token, &fresh_token);
+
+        assert_eq!(parse_session_token(&token), Some((stale_token, fresh_token)));
+        assert_eq!(parse_session_token("no-separator"), None);
+        assert_eq!(parse_session_token(".fresh"), None);
+        assert_eq!(parse_session_token("stale."), None);
+    }
+}
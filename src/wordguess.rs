@@ -1,15 +1,40 @@
-use std::fmt;
+use std::{cmp::Reverse, collections::HashMap, fmt, ops::RangeInclusive, sync::OnceLock};
 
+use actix_web::web;
+use chrono::{Datelike, NaiveDate};
+use lazy_static::lazy_static;
+use rand_chacha::{
+    rand_core::{RngCore, SeedableRng},
+    ChaCha8Rng,
+};
+use schemars::{gen::SchemaGenerator, JsonSchema};
 use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
 
-use crate::{errors::GameError, game::Game};
+use crate::{
+    dictionary::{dictionary_of, LETTERS, WORD_LENGTHS},
+    errors::GameError,
+    game::{Game, Outcome},
+    models::{HintQuery, WordGuessOptions, WordGuessRequest, WordGuessResponse},
+    openapi,
+    registry::{GameContext, GameFuture, GameMetadata},
+    solver::{self, Hint},
+};
 
-static WORD_OF_THE_DAY: &str = "orate";
-static MAXIMUM_GUESSES: usize = 6;
-static LETTERS: usize = 5;
+pub static MAXIMUM_GUESSES: usize = 6;
+/// The guess limits a game can be started with
+pub static GUESS_LIMITS: RangeInclusive<usize> = 1..=20;
+/// Seeds the shuffle of the answer list. Changing it changes every day's answer
+static SCHEDULE_SEED: [u8; 32] = *b"word_games answer schedule v1...";
+
+lazy_static! {
+    /// The shuffled order of the answers of every word length, computed on first use
+    static ref SCHEDULES: HashMap<usize, OnceLock<Vec<usize>>> =
+        WORD_LENGTHS.clone().map(|letters| (letters, OnceLock::new())).collect();
+}
 
 /// Represents the condition of a letter in the word
-#[derive(PartialEq, Debug, Serialize, Deserialize, Clone)]
+#[derive(PartialEq, Eq, Hash, Debug, Serialize, Deserialize, Clone, JsonSchema)]
 enum Condition {
     NotFound,
     Missplaced,
@@ -30,17 +55,36 @@ impl Condition {
 /// Represents a letter in the guessed word\
 /// `value`: The letter\
 /// `condition`: The condition of the letter
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, JsonSchema)]
 struct Letter {
     value: char,
     condition: Condition,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, JsonSchema)]
 pub struct WordResult {
     letters: Vec<Letter>,
 }
 
+impl WordResult {
+    /// Whether every letter is in the right spot
+    pub fn is_solved(&self) -> bool {
+        self.letters
+            .iter()
+            .all(|letter| letter.condition == Condition::Correct)
+    }
+
+    /// The condition of every letter, the feedback without the guess
+    fn pattern(&self) -> Vec<Condition> {
+        self.letters.iter().map(|letter| letter.condition.clone()).collect()
+    }
+
+    /// The number of letters with `condition`
+    fn count(&self, condition: Condition) -> usize {
+        self.letters.iter().filter(|letter| letter.condition == condition).count()
+    }
+}
+
 impl fmt::Display for WordResult {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         for letter in &self.letters {
@@ -50,21 +94,302 @@ impl fmt::Display for WordResult {
     }
 }
 
-#[derive(Debug, Deserialize, Serialize, Clone)]
+/// The answer for a given day
+/// ### Arguments
+/// * `day` - The day of the puzzle
+/// * `letters` - The length of the answer
+/// ### Returns
+/// `None` when there is no dictionary of `letters` letters
+///
+/// The answer list is shuffled with `SCHEDULE_SEED` and walked one word per day,
+/// so every answer is used once before the schedule repeats
+pub fn answer_for(day: NaiveDate, letters: usize) -> Option<String> {
+    answers_for(day, letters, 1).map(|mut answers| answers.remove(0))
+}
+
+/// `count` different answers for a given day, for games with several boards
+/// ### Arguments
+/// * `day` - The day of the puzzle
+/// * `letters` - The length of the answers
+/// * `count` - The number of answers
+/// ### Returns
+/// `None` when there is no dictionary of `letters` letters, or it has fewer than `count` answers
+///
+/// Every day takes the next `count` words of the schedule, so a day never repeats an answer
+pub fn answers_for(day: NaiveDate, letters: usize, count: usize) -> Option<Vec<String>> {
+    let answers = dictionary_of(letters)?.answers();
+    if count == 0 || count > answers.len() {
+        return None;
+    }
+    let order = SCHEDULES.get(&letters)?.get_or_init(|| schedule(answers.len()));
+
+    let start = day.num_days_from_ce() as i64 * count as i64;
+    Some(
+        (0..count as i64)
+            .map(|i| answers[order[(start + i).rem_euclid(answers.len() as i64) as usize]].clone())
+            .collect(),
+    )
+}
+
+/// The indices `0..len` in the order the answers are played.\
+/// `ChaCha8Rng` and a hand written Fisher-Yates shuffle are value-stable, unlike `StdRng` and `SliceRandom`,
+/// so updating rand never changes the answers of past or future days
+fn schedule(len: usize) -> Vec<usize> {
+    let mut rng = ChaCha8Rng::from_seed(SCHEDULE_SEED);
+    let mut order: Vec<usize> = (0..len).collect();
+    for i in (1..len).rev() {
+        order.swap(i, (rng.next_u64() % (i as u64 + 1)) as usize);
+    }
+    order
+}
+
+/// The number of letters of games stored before it was recorded
+fn default_letters() -> usize {
+    LETTERS
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, JsonSchema)]
 pub struct WordGuess {
     pub guesses: Vec<WordResult>,
     #[serde(skip)]
     pub answer: String,
     maximum_guesses: usize,
+    /// The length of the answer. Games stored before it was recorded have 5 letters
+    #[serde(default = "default_letters")]
+    pub letters: usize,
+    /// Every revealed hint has to be used in later guesses
+    #[serde(default)]
+    pub hard_mode: bool,
+    /// The answer is never picked, every guess gets the feedback that keeps the most answers possible
+    #[serde(default)]
+    pub adversarial: bool,
+    /// The day of the puzzle. Games stored before it was recorded default to 1970-01-01
+    #[serde(default)]
+    pub day: NaiveDate,
+    #[serde(default)]
+    pub outcome: Outcome,
+    /// The answer, revealed once the game is over
+    #[serde(default)]
+    pub solution: Option<String>,
 }
 
 impl WordGuess {
-    pub fn new() -> Self {
+    /// Start the 5 letter puzzle for `day`
+    /// ### Arguments
+    /// * `day` - The day of the puzzle, which picks the answer
+    /// * `maximum_guesses` - The number of guesses before the game is lost
+    pub fn new(day: NaiveDate, maximum_guesses: usize) -> Self {
         WordGuess {
             guesses: Vec::new(),
-            answer: WORD_OF_THE_DAY.to_string(),
-            maximum_guesses: MAXIMUM_GUESSES,
+            answer: answer_for(day, LETTERS).unwrap_or_default(),
+            maximum_guesses,
+            letters: LETTERS,
+            hard_mode: false,
+            adversarial: false,
+            day,
+            outcome: Outcome::InProgress,
+            solution: None,
+        }
+    }
+
+    /// Play with an answer of `letters` letters, picked from the dictionary of that length
+    /// ### Errors
+    /// Returns a `GameError::InvalidVariant` when `letters` is outside of `WORD_LENGTHS`
+    pub fn with_letters(mut self, letters: usize) -> Result<Self, GameError> {
+        self.answer = answer_for(self.day, letters)
+            .filter(|_| WORD_LENGTHS.contains(&letters))
+            .ok_or_else(|| {
+                GameError::InvalidVariant(format!(
+                    "Words have {} to {} letters",
+                    WORD_LENGTHS.start(),
+                    WORD_LENGTHS.end()
+                ))
+            })?;
+        self.letters = letters;
+        Ok(self)
+    }
+
+    /// Play with `maximum_guesses` guesses before the game is lost
+    /// ### Errors
+    /// Returns a `GameError::InvalidVariant` when `maximum_guesses` is outside of `GUESS_LIMITS`
+    pub fn with_maximum_guesses(mut self, maximum_guesses: usize) -> Result<Self, GameError> {
+        if !GUESS_LIMITS.contains(&maximum_guesses) {
+            return Err(GameError::InvalidVariant(format!(
+                "A game has {} to {} guesses",
+                GUESS_LIMITS.start(),
+                GUESS_LIMITS.end()
+            )));
+        }
+        self.maximum_guesses = maximum_guesses;
+        Ok(self)
+    }
+
+    /// Play in hard mode, where `Correct` letters stay in place and `Missplaced` letters have to be guessed again
+    pub fn with_hard_mode(mut self, hard_mode: bool) -> Self {
+        self.hard_mode = hard_mode;
+        self
+    }
+
+    /// Play against an adversary, as in Absurdle.\
+    /// There is no answer: every guess gets the feedback that leaves the most answers possible,
+    /// until a guess is the only one left
+    pub fn with_adversarial(mut self, adversarial: bool) -> Self {
+        self.adversarial = adversarial;
+        if adversarial {
+            self.answer = String::new();
+        }
+        self
+    }
+
+    /// Whether `answer` matches the feedback of every guess so far
+    pub fn is_consistent(&self, answer: &str) -> bool {
+        self.guesses
+            .iter()
+            .all(|result| score(&result.to_string().to_lowercase(), answer).pattern() == result.pattern())
+    }
+
+    /// The answers that match the feedback of every guess so far
+    pub fn candidates(&self) -> Vec<&'static String> {
+        let answers = dictionary_of(self.letters).map(|dictionary| dictionary.answers()).unwrap_or_default();
+        answers.iter().filter(|answer| self.is_consistent(answer)).collect()
+    }
+
+    /// The feedback of an adversarial game, which keeps the largest set of candidates possible.\
+    /// Ties go to the feedback revealing the least: fewer `Correct` letters, then fewer `Missplaced` letters,
+    /// then the candidate that comes first in the answer list
+    /// ### Arguments
+    /// * `guess` - The cleaned guess
+    fn adversarial_score(&self, guess: &str) -> WordResult {
+        // The feedback, the size and the first candidate of every set of candidates, keyed by pattern
+        let mut buckets: HashMap<Vec<Condition>, (WordResult, usize, usize)> = HashMap::new();
+        for (index, candidate) in self.candidates().into_iter().enumerate() {
+            let result = score(guess, candidate);
+            buckets.entry(result.pattern()).or_insert((result, 0, index)).1 += 1;
+        }
+
+        buckets
+            .into_values()
+            .min_by_key(|(result, size, first)| {
+                (
+                    Reverse(*size),
+                    result.count(Condition::Correct),
+                    result.count(Condition::Missplaced),
+                    *first,
+                )
+            })
+            .map(|(result, _, _)| result)
+            .unwrap_or_else(|| score(guess, ""))
+    }
+
+    /// The answer shown once the game is over.\
+    /// A won game reveals the guess that solved it, even if the schedule changed since.
+    /// A lost adversarial game reveals the first candidate left
+    fn reveal(&self) -> String {
+        if let Some(solved) = self.guesses.iter().find(|result| result.is_solved()) {
+            return solved.to_string().to_lowercase();
+        }
+        match self.adversarial {
+            true => self.candidates().first().map(|answer| answer.to_string()).unwrap_or_default(),
+            false => self.answer.clone(),
+        }
+    }
+
+    /// The state after a guess got `result`
+    fn record(&self, result: WordResult) -> Self {
+        let mut state = WordGuess {
+            guesses: {
+                let mut new_guesses = self.guesses.clone();
+                new_guesses.push(result);
+                new_guesses
+            },
+            maximum_guesses: self.maximum_guesses,
+            letters: self.letters,
+            hard_mode: self.hard_mode,
+            adversarial: self.adversarial,
+            answer: self.answer.clone(),
+            day: self.day,
+            outcome: Outcome::InProgress,
+            solution: None,
+        };
+
+        state.outcome = state.evaluate();
+        if state.outcome.is_over() {
+            state.solution = Some(state.reveal());
+        }
+        state
+    }
+
+    /// Determine the outcome from the guesses made so far
+    fn evaluate(&self) -> Outcome {
+        if self.guesses.iter().any(WordResult::is_solved) {
+            Outcome::Won
+        } else if self.guesses.len() >= self.maximum_guesses {
+            Outcome::Lost
+        } else {
+            Outcome::InProgress
+        }
+    }
+
+    /// Restore the answer of a stored game.\
+    /// The answer is never serialized, so it has to be recomputed from `day` and `letters`
+    pub fn resume(mut self) -> Self {
+        // Only lengths with a dictionary are stored, `with_letters` rejects the others
+        if !self.adversarial {
+            self.answer = answer_for(self.day, self.letters).unwrap_or_default();
+        }
+        self
+    }
+
+    /// The game as it was before the first guess.\
+    /// Used to rebuild a game by replaying its guesses
+    pub fn restart(&self) -> Self {
+        WordGuess {
+            guesses: Vec::new(),
+            outcome: Outcome::InProgress,
+            solution: None,
+            ..self.clone()
+        }
+    }
+
+    /// Check that a guess uses every hint revealed so far, for hard mode
+    /// ### Arguments
+    /// * `guess` - The lowercase guess, of any length. A guess too short for a correct letter breaks its hint
+    /// ### Errors
+    /// Returns a `GameError::InvalidGuess` naming the first hint that isn't used
+    pub fn check_hints(&self, guess: &str) -> Result<(), GameError> {
+        let guess: Vec<char> = guess.chars().collect();
+
+        for result in &self.guesses {
+            for (position, letter) in result.letters.iter().enumerate() {
+                let value = letter.value.to_ascii_lowercase();
+                if letter.condition == Condition::Correct && guess.get(position) != Some(&value) {
+                    return Err(GameError::InvalidGuess(format!(
+                        "Hard mode: letter {} must be `{}`",
+                        position + 1,
+                        value
+                    )));
+                }
+            }
+
+            // A letter revealed twice has to be guessed twice
+            for letter in result.letters.iter().filter(|l| l.condition != Condition::NotFound) {
+                let value = letter.value.to_ascii_lowercase();
+                let revealed = result
+                    .letters
+                    .iter()
+                    .filter(|l| l.condition != Condition::NotFound && l.value.to_ascii_lowercase() == value)
+                    .count();
+                let guessed = guess.iter().filter(|&&c| c == value).count();
+                if guessed < revealed {
+                    return Err(GameError::InvalidGuess(match revealed {
+                        1 => format!("Hard mode: the guess must contain `{}`", value),
+                        _ => format!("Hard mode: the guess must contain `{}` {} times", value, revealed),
+                    }));
+                }
+            }
         }
+
+        Ok(())
     }
 
     #[allow(dead_code)]
@@ -73,60 +398,90 @@ impl WordGuess {
     }
 }
 
-impl Game<&str, String> for WordGuess {
+/// Score a guess against an answer
+/// ### Arguments
+/// * `guess` - The cleaned guess
+/// * `answer` - The word being guessed
+/// ### Returns
+/// A `WordResult` with a `Condition` for every letter of the guess
+///
+/// Exact matches are consumed first, then a letter is only `Missplaced`
+/// while the answer still has an unmatched copy of it.
+/// Ex. `eerie` against `orate` yields a single `Correct` e and no `Missplaced` e's
+pub fn score(guess: &str, answer: &str) -> WordResult {
+    let guess: Vec<char> = guess.chars().collect();
+    let answer: Vec<char> = answer.chars().collect();
+
+    let mut conditions = vec![Condition::NotFound; guess.len()];
+    // Count the answer letters that weren't matched exactly
+    let mut unmatched: HashMap<char, usize> = HashMap::new();
+
+    // First pass: consume the exact matches
+    for (i, letter) in answer.iter().enumerate() {
+        if guess.get(i) == Some(letter) {
+            conditions[i] = Condition::Correct;
+        } else {
+            *unmatched.entry(*letter).or_insert(0) += 1;
+        }
+    }
+
+    // Second pass: a letter is only misplaced while unmatched copies remain
+    for (i, c) in guess.iter().enumerate() {
+        if conditions[i] == Condition::Correct {
+            continue;
+        }
+        if let Some(remaining) = unmatched.get_mut(c).filter(|remaining| **remaining > 0) {
+            *remaining -= 1;
+            conditions[i] = Condition::Missplaced;
+        }
+    }
+
+    WordResult {
+        letters: guess
+            .into_iter()
+            .zip(conditions)
+            .map(|(value, condition)| Letter { value, condition })
+            .collect(),
+    }
+}
+
+/// Guesses are any string, ex. `&str` when playing and `String` when served
+impl<T: AsRef<str>> Game<T, String> for WordGuess {
     type State = Self;
     type GameError = GameError;
     type GameResult = WordResult;
 
-    fn guess(&self, guess: &str) -> Result<Self, GameError> {
+    fn guess(&self, guess: T) -> Result<Self, GameError> {
+        let guess = guess.as_ref();
         self.clean(guess)?;
 
-        let result = self.process(guess.to_string())?;
-        Ok(WordGuess {
-            guesses: {
-                let mut new_guesses = self.guesses.clone();
-                new_guesses.push(result);
-                new_guesses
-            },
-            maximum_guesses: self.maximum_guesses,
-            answer: self.answer.clone(),
-        })
+        let result = Game::<T, String>::process(self, guess.to_string())?;
+        Ok(self.record(result))
     }
 
     fn process(&self, guess: String) -> Result<WordResult, GameError> {
-        let mut letters = Vec::new();
-        // Iterate through the letters
-        for (i, c) in guess.chars().enumerate() {
-            // Determine if the letter is in the right spot, if it exists, or if it's not found at all
-            // Iterate through the answer
-            let condition = match self.answer.chars().nth(i) {
-                // The answer & guess letter match
-                Some(letter) if letter == c => Condition::Correct,
-                // The letter was found in the answer, but not the correct position
-                Some(_) if self.answer.contains(c) => Condition::Missplaced,
-                // It was not found
-                _ => Condition::NotFound,
-            };
-
-            letters.push(Letter {
-                value: c,
-                condition,
-            });
+        match self.adversarial {
+            true => Ok(self.adversarial_score(&guess)),
+            false => Ok(score(&guess, &self.answer)),
         }
-        Ok(WordResult { letters })
     }
 
-    fn clean(&self, guess: &str) -> Result<String, Self::GameError> {
-        // * Maximum guesses
-        if self.guesses.len() >= MAXIMUM_GUESSES {
-            return Err(GameError::MaximumGuesses);
+    fn clean(&self, guess: T) -> Result<String, Self::GameError> {
+        let guess = guess.as_ref();
+
+        // * The game is already over
+        match self.evaluate() {
+            Outcome::Won => return Err(GameError::GameOver),
+            Outcome::Lost => return Err(GameError::MaximumGuesses),
+            Outcome::InProgress => {}
         }
 
-        // * The guess length is equal to `LETTERS`
-        if guess.chars().count() != LETTERS {
-            return Err(GameError::InvalidGuess(
-                "Guess must be 5 letters".to_string(),
-            ));
+        // * The guess length is equal to `letters`
+        if guess.chars().count() != self.letters {
+            return Err(GameError::InvalidGuess(format!(
+                "Guess must be {} letters",
+                self.letters
+            )));
         }
 
         // * The guess is a valid word
@@ -134,38 +489,177 @@ impl Game<&str, String> for WordGuess {
             return Err(GameError::InvalidGuess("Guess must be a word".to_string()));
         }
 
+        // * The guess is in the word list
+        if !dictionary_of(self.letters).is_some_and(|dictionary| dictionary.is_allowed(&guess.to_lowercase())) {
+            return Err(GameError::NotInWordList(guess.to_lowercase()));
+        }
+
         // * The guess hasn't been made before
         if self
             .guesses
             .iter()
             .any(|g| g.to_string().to_lowercase() == guess.to_lowercase())
         {
-            return Err(GameError::InvalidGuess("Guess already made.".to_string()));
+            return Err(GameError::DuplicateGuess);
         }
 
-        // * The answer hasn't been guessed
-        if self
-            .guesses
-            .iter()
-            .any(|g| g.to_string().to_lowercase() == WORD_OF_THE_DAY)
-        {
-            return Err(GameError::GameOver);
+        // * In hard mode, the guess uses every hint
+        if self.hard_mode {
+            self.check_hints(&guess.to_lowercase())?;
         }
 
         Ok(guess.to_lowercase())
     }
 }
 
+impl GameMetadata for WordGuess {
+    const ROUTE: &'static str = "wordguess";
+    const KEY: &'static str = "word_guess";
+    const NAME: &'static str = "WordGuess";
+    const DESCRIPTION: &'static str =
+        "Guess the word of the day. Every guess tells which letters are in the word, and which are in the right spot";
+
+    type Options = WordGuessOptions;
+    type Request = WordGuessRequest;
+    type Guess = String;
+    type Clean = String;
+    type Feedback = WordResult;
+    type View = WordGuess;
+    type Response = WordGuessResponse;
+
+    fn start<'a>(context: &'a GameContext<'a>, day: NaiveDate, options: &'a WordGuessOptions) -> GameFuture<'a, Self> {
+        let game = WordGuess::new(day, context.config.maximum_guesses)
+            .with_letters(options.letters.unwrap_or(LETTERS))
+            .and_then(|game| match options.maximum_guesses {
+                Some(maximum_guesses) => game.with_maximum_guesses(maximum_guesses),
+                None => Ok(game),
+            })
+            .map(|game| {
+                game.with_hard_mode(options.hard_mode.unwrap_or(false))
+                    .with_adversarial(options.adversarial.unwrap_or(false))
+            });
+        Box::pin(async move { game })
+    }
+
+    fn check_options(&self, options: &WordGuessOptions) -> Result<(), GameError> {
+        if options.letters.is_some_and(|letters| letters != self.letters) {
+            return Err(GameError::VariantMismatch(format!(
+                "Today's game has {} letters",
+                self.letters
+            )));
+        }
+        if options.maximum_guesses.is_some_and(|maximum_guesses| maximum_guesses != self.maximum_guesses) {
+            return Err(GameError::VariantMismatch(format!(
+                "Today's game has {} guesses",
+                self.maximum_guesses
+            )));
+        }
+        if options.adversarial.is_some_and(|adversarial| adversarial != self.adversarial) {
+            return Err(GameError::VariantMismatch(format!(
+                "Today's game is {}adversarial",
+                if self.adversarial { "" } else { "not " }
+            )));
+        }
+        match options.hard_mode {
+            Some(hard_mode) if hard_mode != self.hard_mode => Err(GameError::VariantMismatch(format!(
+                "Today's game was started {} hard mode",
+                if self.hard_mode { "in" } else { "without" }
+            ))),
+            _ => Ok(()),
+        }
+    }
+
+    fn into_guess(request: WordGuessRequest) -> String {
+        request.guess
+    }
+
+    fn day(&self) -> NaiveDate {
+        self.day
+    }
+
+    fn last_result(&self) -> Option<WordResult> {
+        self.guesses.last().cloned()
+    }
+
+    fn restore(self, _guess: String, feedback: WordResult) -> Self {
+        self.record(feedback)
+    }
+
+    /// The answer is never serialized, so it is safe to send the state as is
+    fn view(&self) -> WordGuess {
+        self.clone()
+    }
+
+    /// A guess has always been answered with the state under `game_status`
+    fn guess_response(&self) -> WordGuessResponse {
+        WordGuessResponse {
+            game_status: self.clone(),
+        }
+    }
+
+    fn resume(self) -> Self {
+        WordGuess::resume(self)
+    }
+
+    fn configure(cfg: &mut web::ServiceConfig) {
+        cfg.service(
+            web::resource("/wordguess/hint")
+                // GET /wordguess/hint
+                // Get the candidates left and the best guesses of the player's game
+                .route(web::get().to(solver::get_hint)),
+        );
+    }
+
+    fn paths(generator: &mut SchemaGenerator) -> Map<String, Value> {
+        let hint = generator.subschema_for::<Hint>();
+        let mut parameters = openapi::query_parameters::<HintQuery>();
+        if let (Some(parameters), Value::Array(options)) =
+            (parameters.as_array_mut(), openapi::query_parameters::<WordGuessOptions>())
+        {
+            parameters.extend(options);
+        }
+
+        let mut paths = Map::new();
+        paths.insert(
+            "/wordguess/hint".to_string(),
+            json!({
+                "get": {
+                    "tags": [Self::NAME],
+                    "summary": "Get a hint for today's WordGuess game",
+                    "description": "The answers that match every feedback so far, and the guesses that are expected to narrow them down the most",
+                    "operationId": "get_word_guess_hint",
+                    "security": openapi::session_security(),
+                    "parameters": parameters,
+                    "responses": openapi::with_errors(
+                        json!({ "200": { "description": "The hint", "content": openapi::json_content(hint) } }),
+                        &[400, 401, 409, 500, 503],
+                    ),
+                }
+            }),
+        );
+        paths
+    }
+}
+
 #[cfg(test)]
 mod tests {
+    use std::collections::HashSet;
+
     use super::*;
+    use crate::dictionary::dictionary;
 
-    const WORDS: [&str; 6] = ["peets", "steep", "steer", "orate", "radar", "beats"];
+    const WORDS: [&str; 6] = ["tepee", "steep", "steer", "orate", "radar", "beats"];
     fn setup(answer: Option<&str>) -> WordGuess {
         WordGuess {
             guesses: Vec::new(),
-            answer: answer.unwrap_or(WORD_OF_THE_DAY).to_string(),
+            answer: answer.unwrap_or("orate").to_string(),
             maximum_guesses: MAXIMUM_GUESSES,
+            letters: LETTERS,
+            hard_mode: false,
+            adversarial: false,
+            day: NaiveDate::from_ymd_opt(2025, 1, 1).unwrap(),
+            outcome: Outcome::InProgress,
+            solution: None,
         }
     }
 
@@ -199,9 +693,9 @@ mod tests {
                 _ => copied[2] = 'a',
             }
 
+            // The corrupted word usually isn't in the word list, so score it directly
             let game = setup(Some(word));
-            let guess_result = game.guess(word).unwrap();
-            let first_guess = guess_result.guesses.first().unwrap();
+            let first_guess = Game::<&str, String>::process(&game, copied.iter().collect()).unwrap();
 
             assert_eq!(first_guess.letters[0].condition, Condition::Correct);
             assert_eq!(first_guess.letters[1].condition, Condition::Correct);
@@ -222,4 +716,352 @@ mod tests {
         assert_eq!(first_guess.letters[3].condition, Condition::Missplaced);
         assert_eq!(first_guess.letters[4].condition, Condition::Missplaced);
     }
+
+    #[test]
+    fn test_determine_results_duplicate_letters() {
+        let game = setup(Some("orate"));
+        let guess_result = game.guess("eerie").unwrap();
+        let first_guess = guess_result.guesses.first().unwrap();
+        assert_eq!(first_guess.letters[0].condition, Condition::NotFound);
+        assert_eq!(first_guess.letters[1].condition, Condition::NotFound);
+        assert_eq!(first_guess.letters[2].condition, Condition::Missplaced);
+        assert_eq!(first_guess.letters[3].condition, Condition::NotFound);
+        assert_eq!(first_guess.letters[4].condition, Condition::Correct);
+
+        // The last r is exact, so only one of the other two can be misplaced
+        let game = setup(Some("radar"));
+        let guess_result = game.guess("error").unwrap();
+        let first_guess = guess_result.guesses.first().unwrap();
+        assert_eq!(first_guess.letters[0].condition, Condition::NotFound);
+        assert_eq!(first_guess.letters[1].condition, Condition::Missplaced);
+        assert_eq!(first_guess.letters[2].condition, Condition::NotFound);
+        assert_eq!(first_guess.letters[3].condition, Condition::NotFound);
+        assert_eq!(first_guess.letters[4].condition, Condition::Correct);
+    }
+
+    /// A straightforward scorer used to check `score`.\
+    /// A letter that isn't an exact match is misplaced when fewer of its earlier,
+    /// non-exact copies in the guess have been seen than there are non-exact copies in the answer
+    fn reference_score(guess: &str, answer: &str) -> Vec<Condition> {
+        let guess: Vec<char> = guess.chars().collect();
+        let answer: Vec<char> = answer.chars().collect();
+        let exact = |i: usize| guess[i] == answer[i];
+
+        (0..guess.len())
+            .map(|i| {
+                if exact(i) {
+                    return Condition::Correct;
+                }
+                let seen = (0..i).filter(|&j| guess[j] == guess[i] && !exact(j)).count();
+                let available = (0..answer.len())
+                    .filter(|&j| answer[j] == guess[i] && !exact(j))
+                    .count();
+                if seen < available {
+                    Condition::Missplaced
+                } else {
+                    Condition::NotFound
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_answer_schedule() {
+        let first = NaiveDate::from_ymd_opt(2025, 1, 1).unwrap();
+        let answers = dictionary().answers();
+
+        // The same day always has the same answer
+        assert_eq!(answer_for(first, LETTERS), answer_for(first, LETTERS));
+        assert_eq!(Some(WordGuess::new(first, MAXIMUM_GUESSES).answer), answer_for(first, LETTERS));
+
+        // Every answer is used once per cycle
+        let cycle: HashSet<String> = first
+            .iter_days()
+            .take(answers.len())
+            .map(|day| answer_for(day, LETTERS).unwrap())
+            .collect();
+        assert_eq!(cycle.len(), answers.len());
+        assert!(cycle.iter().all(|answer| answers.contains(answer)));
+    }
+
+    #[test]
+    fn test_schedule_is_stable() {
+        // Pinned so that a dependency update can't silently move every answer
+        assert_eq!(schedule(10), [2, 8, 3, 7, 9, 4, 6, 1, 5, 0]);
+    }
+
+    #[test]
+    fn test_game_over_uses_answer() {
+        let game = setup(Some("crane")).guess("crane").unwrap();
+        assert!(matches!(game.guess("slate"), Err(GameError::GameOver)));
+    }
+
+    #[test]
+    fn test_outcome_won() {
+        let game = setup(Some("crane")).guess("slate").unwrap();
+        assert_eq!(game.outcome, Outcome::InProgress);
+        assert_eq!(game.solution, None);
+
+        let game = game.guess("crane").unwrap();
+        assert_eq!(game.outcome, Outcome::Won);
+        assert_eq!(game.solution.as_deref(), Some("crane"));
+    }
+
+    #[test]
+    fn test_outcome_lost() {
+        let mut game = setup(Some("crane"));
+        for guess in ["slate", "tepee", "steep", "steer", "radar"] {
+            game = game.guess(guess).unwrap();
+            assert_eq!(game.outcome, Outcome::InProgress);
+            assert_eq!(game.solution, None);
+        }
+
+        let game = game.guess("beats").unwrap();
+        assert_eq!(game.outcome, Outcome::Lost);
+        assert_eq!(game.solution.as_deref(), Some("crane"));
+        assert!(matches!(game.guess("crane"), Err(GameError::MaximumGuesses)));
+    }
+
+    #[test]
+    fn test_resume_restores_answer() {
+        let game = WordGuess::new(NaiveDate::from_ymd_opt(2025, 1, 1).unwrap(), MAXIMUM_GUESSES);
+        let stored: WordGuess = serde_json::from_str(&serde_json::to_string(&game).unwrap()).unwrap();
+        assert!(stored.answer.is_empty());
+        assert_eq!(stored.resume().answer, game.answer);
+    }
+
+    #[test]
+    fn test_guess_not_in_word_list() {
+        let game = setup(None);
+        assert!(matches!(game.guess("zzzzz"), Err(GameError::NotInWordList(_))));
+        assert!(game.guess("Crane").is_ok());
+    }
+
+    #[test]
+    fn test_hard_mode_reuses_hints() {
+        // `beats` against `orate`: `a` and `t` are correct, `e` is missplaced
+        let game = setup(None).with_hard_mode(true).guess("beats").unwrap();
+
+        match game.guess("steer") {
+            Err(GameError::InvalidGuess(message)) => assert_eq!(message, "Hard mode: letter 3 must be `a`"),
+            other => panic!("expected an invalid guess, got {:?}", other.map(|_| ())),
+        }
+        match game.guess("boats") {
+            Err(GameError::InvalidGuess(message)) => assert_eq!(message, "Hard mode: the guess must contain `e`"),
+            other => panic!("expected an invalid guess, got {:?}", other.map(|_| ())),
+        }
+        assert!(game.guess("orate").is_ok());
+
+        // A guess too short to hold a correct letter breaks its hint instead of panicking
+        for short in ["be", ""] {
+            match game.check_hints(short) {
+                Err(GameError::InvalidGuess(message)) => assert_eq!(message, "Hard mode: letter 3 must be `a`"),
+                other => panic!("expected an invalid guess, got {:?}", other),
+            }
+        }
+
+        // The same guesses are fine without hard mode
+        let game = setup(None).guess("beats").unwrap();
+        assert!(game.guess("steer").is_ok());
+        assert!(game.guess("boats").is_ok());
+    }
+
+    #[test]
+    fn test_hard_mode_counts_repeated_hints() {
+        // `tepee` against `steep`: two `e`s are revealed, so later guesses need both
+        let game = setup(Some("steep")).with_hard_mode(true).guess("tepee").unwrap();
+        match game.guess("opted") {
+            Err(GameError::InvalidGuess(message)) => assert_eq!(message, "Hard mode: the guess must contain `e` 2 times"),
+            other => panic!("expected an invalid guess, got {:?}", other.map(|_| ())),
+        }
+        assert!(game.guess("steep").is_ok());
+    }
+
+    #[test]
+    fn test_word_lengths() {
+        let day = NaiveDate::from_ymd_opt(2025, 1, 1).unwrap();
+        for letters in WORD_LENGTHS.clone() {
+            let game = WordGuess::new(day, MAXIMUM_GUESSES).with_letters(letters).unwrap();
+            assert_eq!(game.answer.chars().count(), letters);
+            assert!(game.guess(&game.answer).unwrap().outcome.is_over());
+        }
+
+        let game = WordGuess::new(day, MAXIMUM_GUESSES).with_letters(7).unwrap();
+        match game.guess("crane") {
+            Err(GameError::InvalidGuess(message)) => assert_eq!(message, "Guess must be 7 letters"),
+            other => panic!("expected an invalid guess, got {:?}", other.map(|_| ())),
+        }
+        assert!(matches!(game.guess("zzzzzzz"), Err(GameError::NotInWordList(_))));
+        assert!(game.guess("example").is_ok());
+
+        // The answer of a stored game is recomputed for its length
+        let stored: WordGuess = serde_json::from_str(&serde_json::to_string(&game).unwrap()).unwrap();
+        assert_eq!(stored.resume().answer, game.answer);
+
+        for letters in [3, 12] {
+            assert!(matches!(
+                WordGuess::new(day, MAXIMUM_GUESSES).with_letters(letters),
+                Err(GameError::InvalidVariant(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_guess_limit() {
+        let game = setup(Some("crane")).with_maximum_guesses(1).unwrap();
+        let game = game.guess("slate").unwrap();
+        assert_eq!(game.outcome, Outcome::Lost);
+        assert!(matches!(game.guess("crane"), Err(GameError::MaximumGuesses)));
+
+        assert!(matches!(setup(None).with_maximum_guesses(0), Err(GameError::InvalidVariant(_))));
+        assert!(matches!(setup(None).with_maximum_guesses(21), Err(GameError::InvalidVariant(_))));
+    }
+
+    #[test]
+    fn test_legacy_games_have_5_letters() {
+        let mut stored = serde_json::to_value(setup(None)).unwrap();
+        stored.as_object_mut().unwrap().remove("letters");
+        let game: WordGuess = serde_json::from_value(stored).unwrap();
+        assert_eq!(game.letters, LETTERS);
+    }
+
+    #[test]
+    fn test_adversarial_keeps_the_most_candidates() {
+        let game = setup(None).with_adversarial(true);
+        assert!(game.answer.is_empty());
+        let played = game.guess("crane").unwrap();
+
+        // No other feedback would have kept more answers
+        let mut sizes: HashMap<Vec<Condition>, usize> = HashMap::new();
+        for answer in game.candidates() {
+            *sizes.entry(score("crane", answer).pattern()).or_default() += 1;
+        }
+        assert_eq!(played.candidates().len(), *sizes.values().max().unwrap());
+        assert!(!played.guesses[0].is_solved());
+
+        // The same guesses always get the same feedback
+        assert_eq!(game.guess("crane").unwrap().guesses[0].pattern(), played.guesses[0].pattern());
+    }
+
+    #[test]
+    fn test_adversarial_outcome() {
+        let mut game = setup(None).with_adversarial(true).with_maximum_guesses(20).unwrap();
+        while !game.outcome.is_over() {
+            let guess = game.candidates()[0].clone();
+            game = game.guess(&guess).unwrap();
+        }
+        assert_eq!(game.outcome, Outcome::Won);
+        assert_eq!(game.solution, Some(game.guesses.last().unwrap().to_string()));
+
+        // A lost game reveals an answer that matches every feedback
+        let mut game = setup(None).with_adversarial(true);
+        for guess in ["tepee", "steep", "steer", "orate", "radar", "beats"] {
+            game = game.guess(guess).unwrap();
+        }
+        assert_eq!(game.outcome, Outcome::Lost);
+        let solution = game.solution.clone().unwrap();
+        assert!(game.candidates().contains(&&solution));
+    }
+
+    #[test]
+    fn test_adversarial_replay() {
+        let game = setup(None).with_adversarial(true);
+        let played = game.guess("crane").unwrap().guess("slate").unwrap();
+
+        let stored: WordGuess = serde_json::from_str(&serde_json::to_string(&game).unwrap()).unwrap();
+        let guesses = played
+            .guesses
+            .iter()
+            .map(|result| (json!(result.to_string()), json!(result)))
+            .collect();
+        let replayed = stored.replay(guesses).unwrap();
+        assert!(replayed.answer.is_empty());
+        assert_eq!(replayed.candidates(), played.candidates());
+    }
+
+    #[test]
+    fn test_hard_mode_variant() {
+        let game = setup(None).with_hard_mode(true);
+        let options = |hard_mode, letters| WordGuessOptions {
+            hard_mode,
+            letters,
+            ..Default::default()
+        };
+        assert!(game.check_options(&options(Some(true), Some(5))).is_ok());
+        assert!(game.check_options(&WordGuessOptions::default()).is_ok());
+        assert!(matches!(game.check_options(&options(Some(false), None)), Err(GameError::VariantMismatch(_))));
+        assert!(matches!(game.check_options(&options(None, Some(6))), Err(GameError::VariantMismatch(_))));
+    }
+
+    fn conditions(guess: &str, answer: &str) -> Vec<Condition> {
+        score(guess, answer)
+            .letters
+            .into_iter()
+            .map(|letter| letter.condition)
+            .collect()
+    }
+
+    #[test]
+    fn test_score_matches_reference_over_dictionary() {
+        use rand::{rngs::StdRng, seq::SliceRandom, SeedableRng};
+
+        let answers = dictionary().answers();
+        let mut words: Vec<&String> = dictionary().allowed().collect();
+        words.sort();
+
+        // Every word in the dictionary against a fixed sample of answers
+        let mut rng = StdRng::seed_from_u64(5);
+        let sample: Vec<&String> = answers.choose_multiple(&mut rng, 25).collect();
+        for answer in sample {
+            for guess in &words {
+                assert_eq!(
+                    conditions(guess, answer),
+                    reference_score(guess, answer),
+                    "`{}` against `{}`",
+                    guess,
+                    answer
+                );
+            }
+        }
+
+        // Every answer against a fixed sample of words
+        let sample: Vec<&&String> = words.choose_multiple(&mut rng, 25).collect();
+        for guess in sample {
+            for answer in answers {
+                assert_eq!(
+                    conditions(guess, answer),
+                    reference_score(guess, answer),
+                    "`{}` against `{}`",
+                    guess,
+                    answer
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_score_matches_reference() {
+        use rand::{rngs::StdRng, Rng, SeedableRng};
+
+        // A small alphabet forces plenty of repeated letters
+        let alphabet: Vec<char> = "abcde".chars().collect();
+        let mut rng = StdRng::seed_from_u64(5);
+        let mut random_word = || -> String {
+            (0..LETTERS)
+                .map(|_| alphabet[rng.gen_range(0..alphabet.len())])
+                .collect()
+        };
+
+        for _ in 0..20_000 {
+            let guess = random_word();
+            let answer = random_word();
+            assert_eq!(
+                conditions(&guess, &answer),
+                reference_score(&guess, &answer),
+                "`{}` against `{}`",
+                guess,
+                answer
+            );
+        }
+    }
 }